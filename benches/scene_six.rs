@@ -0,0 +1,34 @@
+//! Render time of scene six, the particle system with glass objects.
+//!
+//! Run with `cargo bench`; pass `-- --save-baseline <name>` and
+//! `-- --baseline <name>` to compare two versions of the renderer.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rt_2::scene::Scene;
+use rt_2::scenes;
+
+fn scene_six() -> Scene {
+    // The particle system places its objects with the shared generator
+    rt_2::seed_rng(6);
+    let mut scene = Scene::new();
+    scenes::scene_six(&mut scene);
+    scene.camera_mut().set_resolution((120, 90));
+    scene.set_sample_size(4);
+    scene.set_max_depth(8);
+    scene.set_seed(6);
+    scene
+}
+
+fn render_scene_six(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scene six");
+    group.sample_size(10);
+
+    let mut scene = scene_six();
+    group.bench_function("single thread", |b| b.iter(|| scene.render(false, None)));
+    group.bench_function("parallel", |b| b.iter(|| scene.render(true, None)));
+
+    group.finish();
+}
+
+criterion_group!(benches, render_scene_six);
+criterion_main!(benches);
@@ -1,14 +1,18 @@
 use eframe::{self, egui};
 use rfd::FileDialog;
-use serde_json;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
 // Import the SceneData and related structs from the main project
 use rt_2::core::color::Color;
 use rt_2::core::vec3::{Point3, Vec3};
+use rt_2::objects::ObjModel;
+use rt_2::scene::Camera;
 use rt_2::scene::storage::{
-    CubeData, CylinderData, ObjectData, PlaneData, SceneData, SphereData, TextureData,
+    ClassicMaterialData, CubeData, CylinderData, DirectionalLightData, LightData, MaterialData,
+    MeshData, ObjectData, PbrMaterialData, PlaneData, PointLightData, ProjectionData,
+    RenderData, RotationData, SamplerData, SceneData, SphereData, SpotLightData, TextureData,
+    ToneMappingData, TransformData,
 };
 
 fn point3_editor(ui: &mut egui::Ui, label: &str, point: &mut Point3, scene_changed: &mut bool) {
@@ -92,6 +96,7 @@ struct SceneEditorApp {
     current_view: ViewType,
     current_file_path: Option<PathBuf>,
     image_previews: std::collections::HashMap<String, egui::TextureHandle>,
+    mesh_previews: std::collections::HashMap<String, Option<ObjModel>>,
 }
 
 const LEFT: u8 = 1;
@@ -99,6 +104,32 @@ const RIGHT: u8 = 2;
 const TOP: u8 = 4;
 const BOTTOM: u8 = 8;
 
+/// Checkbox for whether the scene sets `value`, with an editor for it when it does.
+fn optional_value_editor<T: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<T>,
+    default: T,
+    unset_label: &str,
+    scene_changed: &mut bool,
+) {
+    ui.horizontal(|ui| {
+        let mut is_set = value.is_some();
+        if ui.checkbox(&mut is_set, label).changed() {
+            *value = is_set.then_some(default);
+            *scene_changed = true;
+        }
+        match value {
+            Some(value) => {
+                *scene_changed |= ui.add(egui::DragValue::new(value)).changed();
+            }
+            None => {
+                ui.label(unset_label);
+            }
+        }
+    });
+}
+
 fn compute_outcode(p: egui::Pos2, rect: egui::Rect) -> u8 {
     let mut code = 0;
     if p.x < rect.min.x {
@@ -165,7 +196,76 @@ fn cohen_sutherland_clip(
     }
 }
 
+/// `file` relative to the directory `dir`, written with `/` so scene files
+/// stay portable. Both paths must be absolute; a file on another drive keeps
+/// its absolute path.
+fn relative_path(file: &Path, dir: &Path) -> String {
+    // Resolve `..` without touching the file system
+    fn normalize(path: &Path) -> Vec<Component<'_>> {
+        let mut components = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir if matches!(components.last(), Some(Component::Normal(_))) => {
+                    components.pop();
+                }
+                _ => components.push(component),
+            }
+        }
+        components
+    }
+    let file_components = normalize(file);
+    let dir_components = normalize(dir);
+
+    let common = file_components
+        .iter()
+        .zip(&dir_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return file.to_string_lossy().to_string();
+    }
+
+    let ups = (common..dir_components.len()).map(|_| "..".to_string());
+    let downs = file_components[common..]
+        .iter()
+        .map(|c| c.as_os_str().to_string_lossy().to_string());
+    ups.chain(downs).collect::<Vec<_>>().join("/")
+}
+
 impl SceneEditorApp {
+    /// Directory that relative file paths in the scene are resolved from.
+    fn scene_dir(&self) -> PathBuf {
+        let dir = self
+            .current_file_path
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or(Path::new(""));
+        if dir.as_os_str().is_empty() {
+            // An unsaved scene, or one in the working directory
+            return std::env::current_dir().unwrap_or_default();
+        }
+        std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf())
+    }
+
+    /// Point the editor at a new scene file location, rewriting relative
+    /// file paths so they still lead to the same files from there.
+    fn move_scene_file(&mut self, path: PathBuf) {
+        let old_dir = self.scene_dir();
+        self.current_file_path = Some(path);
+        let new_dir = self.scene_dir();
+        if old_dir == new_dir {
+            return;
+        }
+
+        for file in self.scene_data.file_paths_mut() {
+            if !file.is_empty() && Path::new(file.as_str()).is_relative() {
+                *file = relative_path(&old_dir.join(file.as_str()), &new_dir);
+            }
+        }
+        self.mesh_previews.clear();
+    }
+
     fn update_json_string(&mut self) {
         match serde_json::to_string_pretty(&self.scene_data) {
             Ok(json) => {
@@ -312,6 +412,16 @@ impl SceneEditorApp {
 
         // Draw Objects
         for object in &self.scene_data.objects {
+            // Transformed objects are drawn from their projected wireframe
+            if object.transform().is_some() {
+                let stroke = egui::Stroke::new(1.0, Self::object_color(object));
+                for line in self.get_wireframe(object) {
+                    let points = line.into_iter().map(to_screen_pos).collect();
+                    painter.add(egui::Shape::line(points, stroke));
+                }
+                continue;
+            }
+
             match object {
                 ObjectData::Sphere(sphere) => {
                     let center_2d = to_screen_pos(sphere.center);
@@ -372,18 +482,17 @@ impl SceneEditorApp {
                         );
                     }
                     ViewType::Front => {
-                        let half_height = cylinder.height / 2.0;
                         let radius = cylinder.radius;
                         let center = cylinder.center;
 
                         let top_left = to_screen_pos(Point3::new(
                             center.x - radius,
-                            center.y + half_height,
+                            center.y + cylinder.height,
                             center.z,
                         ));
                         let bottom_right = to_screen_pos(Point3::new(
                             center.x + radius,
-                            center.y - half_height,
+                            center.y,
                             center.z,
                         ));
 
@@ -396,18 +505,17 @@ impl SceneEditorApp {
                         );
                     }
                     ViewType::Side => {
-                        let half_height = cylinder.height / 2.0;
                         let radius = cylinder.radius;
                         let center = cylinder.center;
 
                         let top_left = to_screen_pos(Point3::new(
                             center.x,
-                            center.y + half_height,
+                            center.y + cylinder.height,
                             center.z - radius,
                         ));
                         let bottom_right = to_screen_pos(Point3::new(
                             center.x,
-                            center.y - half_height,
+                            center.y,
                             center.z + radius,
                         ));
 
@@ -421,10 +529,53 @@ impl SceneEditorApp {
                     }
                     _ => {}
                 },
+                ObjectData::Mesh(mesh) => {
+                    let stroke = egui::Stroke::new(1.0, egui::Color32::YELLOW);
+                    match self.mesh_previews.get(&mesh.path) {
+                        Some(Some(model)) => {
+                            for face in Self::get_mesh_faces(model, mesh) {
+                                let points = face.map(to_screen_pos).to_vec();
+                                painter.add(egui::Shape::closed_line(points, stroke));
+                            }
+                        }
+                        _ => {
+                            painter.circle_filled(to_screen_pos(mesh.position), 3.0, stroke.color);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Draw Lights
+        for light in &self.scene_data.lights {
+            let stroke = egui::Stroke::new(1.0, Self::light_color(light));
+            for line in Self::get_light_lines(light) {
+                let points = line.into_iter().map(to_screen_pos).collect();
+                painter.add(egui::Shape::line(points, stroke));
+            }
+        }
+    }
+
+    fn load_mesh_previews(&mut self) {
+        let scene_dir = self.scene_dir();
+        for object in &self.scene_data.objects {
+            if let ObjectData::Mesh(mesh) = object {
+                if !mesh.path.is_empty() && !self.mesh_previews.contains_key(&mesh.path) {
+                    let model = ObjModel::load(scene_dir.join(&mesh.path)).ok();
+                    self.mesh_previews.insert(mesh.path.clone(), model);
+                }
             }
         }
     }
 
+    fn get_mesh_faces(model: &ObjModel, mesh: &MeshData) -> Vec<[Point3; 3]> {
+        model
+            .faces
+            .iter()
+            .map(|face| face.map(|c| mesh.position + model.positions[c.position] * mesh.scale))
+            .collect()
+    }
+
     fn get_cube_vertices(center: Point3, size: f32) -> [Point3; 8] {
         let half_size = size / 2.0;
         [
@@ -479,52 +630,236 @@ impl SceneEditorApp {
     ) -> (Vec<Point3>, Vec<Point3>) {
         let mut top_vertices = Vec::new();
         let mut bottom_vertices = Vec::new();
-        let half_height = height / 2.0;
 
+        // The cylinder's center is the middle of its bottom cap
         for i in 0..=segments {
             let angle = i as f32 * 2.0 * std::f32::consts::PI / segments as f32;
             let x = center.x + radius * angle.cos();
             let z = center.z + radius * angle.sin();
-            top_vertices.push(Point3::new(x, center.y + half_height, z));
-            bottom_vertices.push(Point3::new(x, center.y - half_height, z));
+            top_vertices.push(Point3::new(x, center.y + height, z));
+            bottom_vertices.push(Point3::new(x, center.y, z));
         }
 
         (top_vertices, bottom_vertices)
     }
 
-    fn draw_scene_3d(&mut self, ui: &mut egui::Ui) {
-        let painter = ui.painter();
-        let rect = ui.max_rect();
-        painter.rect_filled(rect, 0.0, egui::Color32::DARK_GRAY);
+    fn get_sphere_lines(center: Point3, radius: f32, segments: usize) -> Vec<Vec<Point3>> {
+        let point = |polar: f32, azimuth: f32| {
+            Point3::new(
+                center.x + radius * polar.sin() * azimuth.cos(),
+                center.y + radius * polar.cos(),
+                center.z + radius * polar.sin() * azimuth.sin(),
+            )
+        };
+        let step = std::f32::consts::PI / segments as f32;
+
+        // Meridians (vertical loops)
+        let mut lines: Vec<Vec<Point3>> = (0..segments)
+            .map(|i| {
+                (0..=segments)
+                    .map(|j| point(j as f32 * step, i as f32 * 2.0 * step))
+                    .collect()
+            })
+            .collect();
+
+        // Parallels (horizontal loops)
+        lines.extend((1..segments).map(|i| {
+            (0..=segments)
+                .map(|j| point(i as f32 * step, j as f32 * 2.0 * step))
+                .collect()
+        }));
+
+        lines
+    }
+
+    fn object_color(object: &ObjectData) -> egui::Color32 {
+        match object {
+            ObjectData::Sphere(_) => egui::Color32::BLUE,
+            ObjectData::Plane(_) => egui::Color32::GREEN,
+            ObjectData::Cube(_) => egui::Color32::RED,
+            ObjectData::Cylinder(_) => egui::Color32::from_rgb(255, 0, 255),
+            ObjectData::Mesh(_) => egui::Color32::YELLOW,
+        }
+    }
+
+    /// Outline of an object as world-space polylines, with its transform applied.
+    fn get_wireframe(&self, object: &ObjectData) -> Vec<Vec<Point3>> {
+        let lines = match object {
+            ObjectData::Sphere(sphere) => Self::get_sphere_lines(sphere.center, sphere.radius, 12),
+            ObjectData::Plane(plane) => {
+                let half_x = plane.size.x / 2.0;
+                let half_z = plane.size.z / 2.0;
+                let num_lines = 10;
+                let mut lines = Vec::new();
+                for i in 0..=num_lines {
+                    let p = i as f32 / num_lines as f32;
+                    let x = plane.center.x - half_x + p * plane.size.x;
+                    let z = plane.center.z - half_z + p * plane.size.z;
+                    lines.push(vec![
+                        Point3::new(x, plane.center.y, plane.center.z - half_z),
+                        Point3::new(x, plane.center.y, plane.center.z + half_z),
+                    ]);
+                    lines.push(vec![
+                        Point3::new(plane.center.x - half_x, plane.center.y, z),
+                        Point3::new(plane.center.x + half_x, plane.center.y, z),
+                    ]);
+                }
+                lines
+            }
+            ObjectData::Cube(cube) => {
+                let v = Self::get_cube_vertices(cube.center, cube.size);
+                vec![
+                    vec![v[0], v[1], v[2], v[3], v[0]], // Back face
+                    vec![v[4], v[5], v[6], v[7], v[4]], // Front face
+                    vec![v[0], v[4]],
+                    vec![v[1], v[5]],
+                    vec![v[2], v[6]],
+                    vec![v[3], v[7]],
+                ]
+            }
+            ObjectData::Cylinder(cylinder) => {
+                let num_segments = 12;
+                let (top, bottom) = Self::get_cylinder_vertices(
+                    cylinder.center,
+                    cylinder.radius,
+                    cylinder.height,
+                    num_segments,
+                );
+                let mut lines: Vec<Vec<Point3>> =
+                    (0..num_segments).map(|i| vec![top[i], bottom[i]]).collect();
+                lines.push(top);
+                lines.push(bottom);
+                lines
+            }
+            ObjectData::Mesh(mesh) => match self.mesh_previews.get(&mesh.path) {
+                Some(Some(model)) => Self::get_mesh_faces(model, mesh)
+                    .into_iter()
+                    .map(|[a, b, c]| vec![a, b, c, a])
+                    .collect(),
+                _ => Vec::new(),
+            },
+        };
 
-        let camera = &self.scene_data.camera;
-        let fov_rad = camera.fov.to_radians();
-        let aspect_ratio = camera.aspect_ratio;
+        match object.transform_matrix() {
+            Some(matrix) => lines
+                .into_iter()
+                .map(|line| {
+                    line.into_iter()
+                        .map(|p| matrix.transform_point3(p.into()).into())
+                        .collect()
+                })
+                .collect(),
+            None => lines,
+        }
+    }
 
-        let view_matrix =
-            glam::Mat4::look_at_rh(camera.position.into(), camera.look_at.into(), camera.up.into());
-        let projection_matrix = glam::Mat4::perspective_rh(fov_rad, aspect_ratio, 0.1, 100.0);
-        let transform = projection_matrix * view_matrix;
+    fn light_color(light: &LightData) -> egui::Color32 {
+        let color = match light {
+            LightData::Point(p) => p.color,
+            LightData::Spot(s) => s.color,
+            LightData::Directional(d) => d.color,
+        };
+        egui::Color32::from_rgb(
+            (color.r.clamp(0.0, 1.0) * 255.0) as u8,
+            (color.g.clamp(0.0, 1.0) * 255.0) as u8,
+            (color.b.clamp(0.0, 1.0) * 255.0) as u8,
+        )
+    }
 
-        let to_screen_pos = |p: Point3| {
-            let p_world = glam::vec4(p.x, p.y, p.z, 1.0);
-            let p_clip = transform * p_world;
+    /// Gizmo for a light as world-space polylines: a small star at point and
+    /// spot lights, the cone of a spot light, and an arrow towards the origin
+    /// for a directional light.
+    fn get_light_lines(light: &LightData) -> Vec<Vec<Point3>> {
+        let star = |p: Point3| {
+            let r = 0.3;
+            vec![
+                vec![p - Vec3::new(r, 0.0, 0.0), p + Vec3::new(r, 0.0, 0.0)],
+                vec![p - Vec3::new(0.0, r, 0.0), p + Vec3::new(0.0, r, 0.0)],
+                vec![p - Vec3::new(0.0, 0.0, r), p + Vec3::new(0.0, 0.0, r)],
+            ]
+        };
+
+        match light {
+            LightData::Point(point) => star(point.position),
+            LightData::Spot(spot) => {
+                let mut lines = star(spot.position);
+                let direction = spot.direction.normalize();
+                if direction.near_zero() {
+                    return lines;
+                }
+
+                let length = 2.0;
+                let radius = length * spot.angle.clamp(0.0, 89.0).to_radians().tan();
+                let (u, v) = direction.tangent_frame();
+                let base = spot.position + direction * length;
+                let segments = 16;
+                let rim: Vec<Point3> = (0..=segments)
+                    .map(|i| {
+                        let angle = i as f32 * 2.0 * std::f32::consts::PI / segments as f32;
+                        base + (u * angle.cos() + v * angle.sin()) * radius
+                    })
+                    .collect();
+
+                for i in (0..segments).step_by(segments / 4) {
+                    lines.push(vec![spot.position, rim[i]]);
+                }
+                lines.push(rim);
+                lines
+            }
+            LightData::Directional(directional) => {
+                let direction = directional.direction.normalize();
+                if direction.near_zero() {
+                    return Vec::new();
+                }
 
-            if p_clip.w <= 0.0 {
-                return egui::pos2(-1000.0, -1000.0); // Off-screen
+                let (u, _) = direction.tangent_frame();
+                let tip = Point3::ZERO - direction * 1.5;
+                let tail = Point3::ZERO - direction * 4.0;
+                let head = tip - direction * 0.4;
+                vec![
+                    vec![tail, tip],
+                    vec![head + u * 0.2, tip, head - u * 0.2],
+                ]
             }
+        }
+    }
 
-            let p_ndc = p_clip / p_clip.w;
+    fn draw_scene_3d(&mut self, ui: &mut egui::Ui) {
+        let panel = ui.max_rect();
+        ui.painter().rect_filled(panel, 0.0, egui::Color32::from_gray(40));
+
+        // Show what the render will, letterboxed to the shape of the output image
+        let camera_data = &self.scene_data.camera;
+        let (width, height) = camera_data.resolution;
+        let image_aspect = width.max(1) as f32 / height.max(1) as f32;
+        let view_size = if panel.aspect_ratio() > image_aspect {
+            egui::vec2(panel.height() * image_aspect, panel.height())
+        } else {
+            egui::vec2(panel.width(), panel.width() / image_aspect)
+        };
+        let rect = egui::Rect::from_center_size(panel.center(), view_size);
+        let painter = ui.painter().with_clip_rect(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::DARK_GRAY);
 
-            let screen_x = rect.center().x + p_ndc.x * rect.width() / 2.0;
-            let screen_y = rect.center().y - p_ndc.y * rect.height() / 2.0;
+        // Same camera the renderer builds, so the preview matches the render
+        let camera = Camera::from(camera_data.clone());
+        let view_matrix = glam::Mat4::look_at_rh(
+            camera_data.position.into(),
+            camera_data.look_at.into(),
+            camera_data.up.into(),
+        );
 
-            egui::pos2(screen_x, screen_y)
+        let to_screen_pos = |p: Point3| match camera.project(p) {
+            Some((x, y)) => egui::pos2(rect.left() + x * rect.width(), rect.bottom() - y * rect.height()),
+            None => egui::pos2(-1000.0, -1000.0), // Off-screen
         };
 
         // --- Pass 1: Draw Planes ---
         for object in &self.scene_data.objects {
             if let ObjectData::Plane(plane) = object {
+                if plane.transform.is_some() {
+                    continue; // Drawn with the other transformed objects
+                }
                 let stroke = egui::Stroke::new(1.0, egui::Color32::GREEN);
                 let half_size_x = plane.size.x / 2.0;
                 let half_size_z = plane.size.z / 2.0;
@@ -582,42 +917,28 @@ impl SceneEditorApp {
 
         // --- Pass 2: Draw other objects ---
         for object in &self.scene_data.objects {
+            if object.transform().is_some() {
+                let stroke = egui::Stroke::new(1.0, Self::object_color(object));
+                for line in self.get_wireframe(object) {
+                    for segment in line.windows(2) {
+                        let start = to_screen_pos(segment[0]);
+                        let end = to_screen_pos(segment[1]);
+                        if start == egui::pos2(-1000.0, -1000.0) || end == egui::pos2(-1000.0, -1000.0)
+                        {
+                            continue;
+                        }
+                        painter.line_segment([start, end], stroke);
+                    }
+                }
+                continue;
+            }
+
             match object {
                 ObjectData::Plane(_) => continue, // Skip planes, already drawn
                 ObjectData::Sphere(sphere) => {
-                    let num_segments = 12;
                     let stroke = egui::Stroke::new(1.0, egui::Color32::BLUE);
-
-                    // Draw meridians (vertical loops)
-                    for i in 0..num_segments {
-                        let angle = i as f32 * std::f32::consts::PI * 2.0 / num_segments as f32;
-                        let mut points = Vec::new();
-                        for j in 0..=num_segments {
-                            let sub_angle = j as f32 * std::f32::consts::PI / num_segments as f32;
-                            let x =
-                                sphere.center.x + sphere.radius * sub_angle.sin() * angle.cos();
-                            let y = sphere.center.y + sphere.radius * sub_angle.cos();
-                            let z =
-                                sphere.center.z + sphere.radius * sub_angle.sin() * angle.sin();
-                            points.push(to_screen_pos(Point3::new(x, y, z)));
-                        }
-                        painter.add(egui::Shape::line(points, stroke));
-                    }
-
-                    // Draw parallels (horizontal loops)
-                    for i in 1..num_segments {
-                        let sub_angle = i as f32 * std::f32::consts::PI / num_segments as f32;
-                        let mut points = Vec::new();
-                        for j in 0..=num_segments {
-                            let angle =
-                                j as f32 * std::f32::consts::PI * 2.0 / num_segments as f32;
-                            let x =
-                                sphere.center.x + sphere.radius * sub_angle.sin() * angle.cos();
-                            let y = sphere.center.y + sphere.radius * sub_angle.cos();
-                            let z =
-                                sphere.center.z + sphere.radius * sub_angle.sin() * angle.sin();
-                            points.push(to_screen_pos(Point3::new(x, y, z)));
-                        }
+                    for line in Self::get_sphere_lines(sphere.center, sphere.radius, 12) {
+                        let points = line.into_iter().map(to_screen_pos).collect();
                         painter.add(egui::Shape::line(points, stroke));
                     }
                 }
@@ -676,6 +997,33 @@ impl SceneEditorApp {
                         );
                     }
                 }
+                ObjectData::Mesh(mesh) => {
+                    let stroke = egui::Stroke::new(1.0, egui::Color32::YELLOW);
+                    if let Some(Some(model)) = self.mesh_previews.get(&mesh.path) {
+                        for face in Self::get_mesh_faces(model, mesh) {
+                            let points = face.map(to_screen_pos);
+                            if points.contains(&egui::pos2(-1000.0, -1000.0)) {
+                                continue;
+                            }
+                            painter.add(egui::Shape::closed_line(points.to_vec(), stroke));
+                        }
+                    }
+                }
+            }
+        }
+
+        // --- Pass 3: Draw lights ---
+        for light in &self.scene_data.lights {
+            let stroke = egui::Stroke::new(1.0, Self::light_color(light));
+            for line in Self::get_light_lines(light) {
+                for segment in line.windows(2) {
+                    let start = to_screen_pos(segment[0]);
+                    let end = to_screen_pos(segment[1]);
+                    if start == egui::pos2(-1000.0, -1000.0) || end == egui::pos2(-1000.0, -1000.0) {
+                        continue;
+                    }
+                    painter.line_segment([start, end], stroke);
+                }
             }
         }
 
@@ -733,67 +1081,129 @@ impl SceneEditorApp {
 }
 fn material_editor(
     ui: &mut egui::Ui,
-    material: &mut rt_2::scene::storage::MaterialData,
+    material: &mut MaterialData,
     scene_changed: &mut bool,
 ) {
     ui.group(|ui| {
         ui.label("Material Properties");
         ui.horizontal(|ui| {
-            ui.label("Diffuse:");
-            *scene_changed |= ui
-                .add(
-                    egui::DragValue::new(&mut material.diffuse)
-                        .speed(0.01)
-                        .range(0.0..=1.0),
-                )
-                .changed();
-        });
-        ui.horizontal(|ui| {
-            ui.label("Reflectivity:");
-            *scene_changed |= ui
-                .add(
-                    egui::DragValue::new(&mut material.reflectivity)
-                        .speed(0.01)
-                        .range(0.0..=1.0),
-                )
-                .changed();
-        });
-        ui.horizontal(|ui| {
-            ui.label("Transparency:");
-            *scene_changed |= ui
-                .add(
-                    egui::DragValue::new(&mut material.transparency)
-                        .speed(0.01)
-                        .range(0.0..=1.0),
-                )
-                .changed();
-        });
-        ui.horizontal(|ui| {
-            ui.label("Index of Refraction:");
-            *scene_changed |= ui
-                .add(
-                    egui::DragValue::new(&mut material.index_of_refraction)
-                        .speed(0.01)
-                        .range(0.0..=3.0),
-                )
-                .changed();
+            // Switching models keeps the texture and emission
+            let mut is_pbr = matches!(material, MaterialData::Pbr(_));
+            let texture = material.texture_mut().clone();
+            let emission = *material.emission_mut();
+            if ui.radio_value(&mut is_pbr, false, "Classic").changed() {
+                *material = MaterialData::Classic(ClassicMaterialData {
+                    texture: texture.clone(),
+                    diffuse: 1.0,
+                    emission,
+                    ..Default::default()
+                });
+                *scene_changed = true;
+            }
+            if ui.radio_value(&mut is_pbr, true, "PBR").changed() {
+                *material = MaterialData::Pbr(PbrMaterialData {
+                    base_color: texture,
+                    emission,
+                    ..Default::default()
+                });
+                *scene_changed = true;
+            }
         });
+
+        match material {
+            MaterialData::Classic(material) => {
+                ui.horizontal(|ui| {
+                    ui.label("Diffuse:");
+                    *scene_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut material.diffuse)
+                                .speed(0.01)
+                                .range(0.0..=1.0),
+                        )
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Reflectivity:");
+                    *scene_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut material.reflectivity)
+                                .speed(0.01)
+                                .range(0.0..=1.0),
+                        )
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Transparency:");
+                    *scene_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut material.transparency)
+                                .speed(0.01)
+                                .range(0.0..=1.0),
+                        )
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Index of Refraction:");
+                    *scene_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut material.index_of_refraction)
+                                .speed(0.01)
+                                .range(0.0..=3.0),
+                        )
+                        .changed();
+                });
+            }
+            MaterialData::Pbr(material) => {
+                ui.horizontal(|ui| {
+                    ui.label("Metallic:");
+                    *scene_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut material.metallic)
+                                .speed(0.01)
+                                .range(0.0..=1.0),
+                        )
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Roughness:");
+                    *scene_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut material.roughness)
+                                .speed(0.01)
+                                .range(0.0..=1.0),
+                        )
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Specular:");
+                    *scene_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut material.specular)
+                                .speed(0.01)
+                                .range(0.0..=1.0),
+                        )
+                        .changed();
+                });
+            }
+        }
+
+        let emission = material.emission_mut();
         ui.horizontal(|ui| {
             ui.label("Emission:");
-            let mut emission_enabled = material.emission.is_some();
+            let mut emission_enabled = emission.is_some();
             if ui
                 .checkbox(&mut emission_enabled, "Enable Emission")
                 .changed()
             {
                 if emission_enabled {
-                    material.emission = Some(Color::WHITE);
+                    *emission = Some(Color::WHITE);
                 } else {
-                    material.emission = None;
+                    *emission = None;
                 }
                 *scene_changed = true;
             }
         });
-        if let Some(emission_color) = &mut material.emission {
+        if let Some(emission_color) = emission {
             let mut intensity = emission_color.r.max(emission_color.g).max(emission_color.b);
             if intensity == 0.0 {
                 intensity = 1.0;
@@ -823,12 +1233,61 @@ fn material_editor(
     });
 }
 
+fn transform_editor(
+    ui: &mut egui::Ui,
+    transform: &mut Option<TransformData>,
+    scene_changed: &mut bool,
+) {
+    ui.group(|ui| {
+        let mut enabled = transform.is_some();
+        if ui.checkbox(&mut enabled, "Transform").changed() {
+            *transform = enabled.then(TransformData::default);
+            *scene_changed = true;
+        }
+
+        if let Some(transform) = transform {
+            vec3_editor(ui, "Translation:", &mut transform.translation, scene_changed);
+
+            let mut is_euler = matches!(transform.rotation, RotationData::Euler(_));
+            ui.horizontal(|ui| {
+                ui.label("Rotation:");
+                if ui.radio_value(&mut is_euler, true, "Euler").changed() {
+                    transform.rotation = RotationData::Euler(Vec3::ZERO);
+                    *scene_changed = true;
+                }
+                if ui.radio_value(&mut is_euler, false, "Axis-Angle").changed() {
+                    transform.rotation = RotationData::AxisAngle {
+                        axis: Vec3::Y,
+                        angle: 0.0,
+                    };
+                    *scene_changed = true;
+                }
+            });
+            match &mut transform.rotation {
+                RotationData::Euler(angles) => {
+                    vec3_editor(ui, "Degrees:", angles, scene_changed);
+                }
+                RotationData::AxisAngle { axis, angle } => {
+                    vec3_editor(ui, "Axis:", axis, scene_changed);
+                    ui.horizontal(|ui| {
+                        ui.label("Angle:");
+                        *scene_changed |= ui.add(egui::DragValue::new(angle).speed(1.0)).changed();
+                    });
+                }
+            }
+
+            vec3_editor(ui, "Scale:", &mut transform.scale, scene_changed);
+        }
+    });
+}
+
 fn texture_editor(
     ui: &mut egui::Ui,
     texture: &mut TextureData,
     scene_changed: &mut bool,
     ctx: &egui::Context,
     image_previews: &mut std::collections::HashMap<String, egui::TextureHandle>,
+    scene_dir: &Path,
 ) {
     let mut current_texture_type = match texture {
         TextureData::SolidColor(_) => "SolidColor",
@@ -897,15 +1356,17 @@ fn texture_editor(
                         .add_filter("Image Files", &["png", "jpg", "jpeg", "gif", "bmp"])
                         .pick_file()
                     {
-                        *path = new_path.to_string_lossy().to_string();
+                        *path = relative_path(&new_path, scene_dir);
                         *scene_changed = true;
                     }
                 }
             });
 
             if !path.is_empty() {
-                if !image_previews.contains_key(path) {
-                    if let Ok(image) = rt_2::pixels::image::Image::load(path) {
+                // Keyed by the resolved file, which a relative path alone does not pin down
+                let file = scene_dir.join(&*path).to_string_lossy().to_string();
+                if !image_previews.contains_key(&file) {
+                    if let Ok(image) = rt_2::pixels::image::Image::load(&file) {
                         let color_image = egui::ColorImage::from_rgb(
                             [image.width, image.height],
                             &image
@@ -921,12 +1382,12 @@ fn texture_editor(
                                 .collect::<Vec<u8>>(),
                         );
                         let handle =
-                            ctx.load_texture(path.clone(), color_image, Default::default());
-                        image_previews.insert(path.clone(), handle);
+                            ctx.load_texture(file.clone(), color_image, Default::default());
+                        image_previews.insert(file.clone(), handle);
                     }
                 }
 
-                if let Some(texture_handle) = image_previews.get(path) {
+                if let Some(texture_handle) = image_previews.get(&file) {
                     ui.image((texture_handle.id(), texture_handle.size_vec2() / 2.0));
                 }
             }
@@ -943,6 +1404,7 @@ impl Default for SceneEditorApp {
             current_view: ViewType::TopDown,
             current_file_path: None,
             image_previews: std::collections::HashMap::new(),
+            mesh_previews: std::collections::HashMap::new(),
         };
         app.update_json_string(); // Initialize json_string with default scene_data
         app
@@ -952,6 +1414,7 @@ impl Default for SceneEditorApp {
 impl eframe::App for SceneEditorApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let mut scene_changed = false;
+        let scene_dir = self.scene_dir();
 
         egui::SidePanel::left("scene_editor_panel")
             .min_width(250.0)
@@ -972,10 +1435,11 @@ impl eframe::App for SceneEditorApp {
                                 FileDialog::new().add_filter("JSON", &["json"]).pick_file()
                             {
                                 self.current_file_path = Some(path.clone());
+                                self.mesh_previews.clear();
                                 match fs::read_to_string(path) {
                                     Ok(data) => {
                                         self.json_string = data.clone();
-                                        match serde_json::from_str::<SceneData>(&data) {
+                                        match SceneData::from_json(&data) {
                                             Ok(scene) => {
                                                 self.scene_data = scene;
                                                 self.error_message = None;
@@ -1018,7 +1482,7 @@ impl eframe::App for SceneEditorApp {
                                 if let Some(path) =
                                     FileDialog::new().add_filter("JSON", &["json"]).save_file()
                                 {
-                                    self.current_file_path = Some(path.clone());
+                                    self.move_scene_file(path.clone());
                                     match serde_json::to_string_pretty(&self.scene_data) {
                                         Ok(json) => match fs::write(path, json) {
                                             Ok(_) => self.error_message = None,
@@ -1040,30 +1504,28 @@ impl eframe::App for SceneEditorApp {
                             }
                         }
 
-                        if self.current_file_path.is_some() {
-                            if ui.button("Save As").clicked() {
-                                if let Some(path) =
-                                    FileDialog::new().add_filter("JSON", &["json"]).save_file()
-                                {
-                                    self.current_file_path = Some(path.clone());
-                                    match serde_json::to_string_pretty(&self.scene_data) {
-                                        Ok(json) => match fs::write(path, json) {
-                                            Ok(_) => {
-                                                self.error_message = None;
-                                            }
-                                            Err(e) => {
-                                                self.error_message = Some(format!(
-                                                    "Failed to write scene file: {}",
-                                                    e
-                                                ));
-                                            }
-                                        },
+                        if self.current_file_path.is_some() && ui.button("Save As").clicked() {
+                            if let Some(path) =
+                                FileDialog::new().add_filter("JSON", &["json"]).save_file()
+                            {
+                                self.move_scene_file(path.clone());
+                                match serde_json::to_string_pretty(&self.scene_data) {
+                                    Ok(json) => match fs::write(path, json) {
+                                        Ok(_) => {
+                                            self.error_message = None;
+                                        }
                                         Err(e) => {
                                             self.error_message = Some(format!(
-                                                "Failed to serialize scene data: {}",
+                                                "Failed to write scene file: {}",
                                                 e
                                             ));
                                         }
+                                    },
+                                    Err(e) => {
+                                        self.error_message = Some(format!(
+                                            "Failed to serialize scene data: {}",
+                                            e
+                                        ));
                                     }
                                 }
                             }
@@ -1084,6 +1546,7 @@ impl eframe::App for SceneEditorApp {
                             &mut scene_changed,
                             ctx,
                             &mut self.image_previews,
+                            &scene_dir,
                         );
                     });
 
@@ -1117,14 +1580,6 @@ impl eframe::App for SceneEditorApp {
                                         .speed(1.0),
                                 )
                                 .changed();
-
-                            ui.label("Aspect Ratio:");
-                            scene_changed |= ui
-                                .add(
-                                    egui::DragValue::new(&mut self.scene_data.camera.aspect_ratio)
-                                        .speed(0.01),
-                                )
-                                .changed();
                         });
 
                         ui.horizontal(|ui| {
@@ -1142,6 +1597,252 @@ impl eframe::App for SceneEditorApp {
                                 )
                                 .changed();
                         });
+
+                        ui.horizontal(|ui| {
+                            let camera = &mut self.scene_data.camera;
+                            let mut fixed_aspect = camera.aspect_ratio.is_some();
+                            if ui.checkbox(&mut fixed_aspect, "Aspect Ratio:").changed() {
+                                // Start from the shape the resolution gave
+                                let (width, height) = camera.resolution;
+                                camera.aspect_ratio = fixed_aspect
+                                    .then(|| width.max(1) as f32 / height.max(1) as f32);
+                                scene_changed = true;
+                            }
+                            match &mut camera.aspect_ratio {
+                                Some(ratio) => {
+                                    scene_changed |= ui
+                                        .add(
+                                            egui::DragValue::new(ratio)
+                                                .speed(0.01)
+                                                .range(0.01..=f32::MAX),
+                                        )
+                                        .changed();
+                                }
+                                None => {
+                                    ui.label("from resolution");
+                                }
+                            }
+                        });
+
+                        ui.label("Projection:");
+                        let projection = &mut self.scene_data.camera.projection;
+                        let mut current_projection = match projection {
+                            ProjectionData::Perspective => "Perspective",
+                            ProjectionData::Orthographic { .. } => "Orthographic",
+                            ProjectionData::Fisheye { .. } => "Fisheye",
+                            ProjectionData::Equirectangular => "Equirectangular",
+                        };
+                        ui.horizontal_wrapped(|ui| {
+                            for (name, default) in [
+                                ("Perspective", ProjectionData::Perspective),
+                                ("Orthographic", ProjectionData::Orthographic { width: 10.0 }),
+                                ("Fisheye", ProjectionData::Fisheye { fov: 180.0 }),
+                                ("Equirectangular", ProjectionData::Equirectangular),
+                            ] {
+                                if ui.radio_value(&mut current_projection, name, name).changed() {
+                                    *projection = default;
+                                    scene_changed = true;
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            match projection {
+                                ProjectionData::Orthographic { width } => {
+                                    ui.label("Width:");
+                                    scene_changed |= ui
+                                        .add(
+                                            egui::DragValue::new(width)
+                                                .speed(0.1)
+                                                .range(0.01..=f32::MAX),
+                                        )
+                                        .changed();
+                                }
+                                ProjectionData::Fisheye { fov } => {
+                                    ui.label("FOV:");
+                                    scene_changed |= ui
+                                        .add(
+                                            egui::DragValue::new(fov)
+                                                .speed(1.0)
+                                                .range(1.0..=360.0),
+                                        )
+                                        .changed();
+                                }
+                                _ => {}
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Aperture:");
+                            scene_changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut self.scene_data.camera.aperture)
+                                        .speed(0.01)
+                                        .range(0.0..=f32::MAX),
+                                )
+                                .changed();
+
+                            ui.label("Blades:");
+                            scene_changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut self.scene_data.camera.blades)
+                                        .speed(0.1)
+                                        .range(0..=16),
+                                )
+                                .changed();
+                        });
+
+                        ui.horizontal(|ui| {
+                            let camera = &mut self.scene_data.camera;
+                            let mut manual_focus = camera.focus_distance.is_some();
+                            if ui.checkbox(&mut manual_focus, "Focus Distance:").changed() {
+                                // Start manual focus from where the automatic focus was
+                                camera.focus_distance = manual_focus
+                                    .then(|| camera.position.distance(camera.look_at));
+                                scene_changed = true;
+                            }
+                            match &mut camera.focus_distance {
+                                Some(distance) => {
+                                    scene_changed |= ui
+                                        .add(
+                                            egui::DragValue::new(distance)
+                                                .speed(0.1)
+                                                .range(0.0..=f32::MAX),
+                                        )
+                                        .changed();
+                                }
+                                None => {
+                                    ui.label("at Look At");
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            let camera = &mut self.scene_data.camera;
+                            ui.label("Near Clip:");
+                            scene_changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut camera.near)
+                                        .speed(0.1)
+                                        .range(0.0..=f32::MAX),
+                                )
+                                .changed();
+
+                            let mut has_far = camera.far.is_some();
+                            if ui.checkbox(&mut has_far, "Far Clip:").changed() {
+                                camera.far = has_far.then_some(camera.near + 100.0);
+                                scene_changed = true;
+                            }
+                            match &mut camera.far {
+                                Some(far) => {
+                                    scene_changed |= ui
+                                        .add(
+                                            egui::DragValue::new(far)
+                                                .speed(0.1)
+                                                .range(0.0..=f32::MAX),
+                                        )
+                                        .changed();
+                                }
+                                None => {
+                                    ui.label("unbounded");
+                                }
+                            }
+                        });
+                    });
+
+                    ui.separator();
+
+                    // Render Settings Editor
+                    ui.collapsing("Render", |ui| {
+                        let render = &mut self.scene_data.render;
+                        optional_value_editor(
+                            ui,
+                            "Samples:",
+                            &mut render.samples,
+                            RenderData::DEFAULT_SAMPLES,
+                            &format!("default ({})", RenderData::DEFAULT_SAMPLES),
+                            &mut scene_changed,
+                        );
+                        optional_value_editor(
+                            ui,
+                            "Max Depth:",
+                            &mut render.max_depth,
+                            RenderData::DEFAULT_MAX_DEPTH,
+                            &format!("default ({})", RenderData::DEFAULT_MAX_DEPTH),
+                            &mut scene_changed,
+                        );
+                        optional_value_editor(
+                            ui,
+                            "Roulette Depth:",
+                            &mut render.roulette_depth,
+                            3,
+                            "default (3)",
+                            &mut scene_changed,
+                        );
+                        optional_value_editor(
+                            ui,
+                            "Seed:",
+                            &mut render.seed,
+                            0,
+                            "random",
+                            &mut scene_changed,
+                        );
+
+                        ui.horizontal(|ui| {
+                            let mut has_output = render.output.is_some();
+                            if ui.checkbox(&mut has_output, "Output:").changed() {
+                                render.output = has_output.then(|| "output.png".to_string());
+                                scene_changed = true;
+                            }
+                            match &mut render.output {
+                                Some(output) => {
+                                    scene_changed |= ui
+                                        .add_sized([200.0, 20.0], egui::TextEdit::singleline(output))
+                                        .changed();
+                                }
+                                None => {
+                                    ui.label("default (output.ppm)");
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Sampler:");
+                            for (sampler, name) in [
+                                (SamplerData::Independent, "Independent"),
+                                (SamplerData::Stratified, "Stratified"),
+                                (SamplerData::Halton, "Halton"),
+                                (SamplerData::Sobol, "Sobol"),
+                            ] {
+                                scene_changed |= ui
+                                    .radio_value(&mut render.sampler, sampler, name)
+                                    .changed();
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Tone Mapping:");
+                            for (tone_mapping, name) in [
+                                (ToneMappingData::None, "None"),
+                                (ToneMappingData::Reinhard, "Reinhard"),
+                                (ToneMappingData::Aces, "ACES"),
+                            ] {
+                                scene_changed |= ui
+                                    .radio_value(&mut render.tone_mapping, tone_mapping, name)
+                                    .changed();
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Exposure:");
+                            scene_changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut render.exposure)
+                                        .speed(0.1)
+                                        .suffix(" stops"),
+                                )
+                                .changed();
+                        });
                     });
 
                     ui.separator();
@@ -1165,6 +1866,7 @@ impl eframe::App for SceneEditorApp {
                                         ObjectData::Plane(_) => "Plane",
                                         ObjectData::Cube(_) => "Cube",
                                         ObjectData::Cylinder(_) => "Cylinder",
+                                        ObjectData::Mesh(_) => "Mesh",
                                     };
 
                                     ui.horizontal(|ui| {
@@ -1204,6 +1906,13 @@ impl eframe::App for SceneEditorApp {
                                             *object = ObjectData::Cylinder(CylinderData::default());
                                             scene_changed = true;
                                         }
+                                        if ui
+                                            .radio_value(&mut current_object_type, "Mesh", "Mesh")
+                                            .changed()
+                                        {
+                                            *object = ObjectData::Mesh(MeshData::default());
+                                            scene_changed = true;
+                                        }
                                     });
 
                                     match object {
@@ -1227,10 +1936,11 @@ impl eframe::App for SceneEditorApp {
                                                 ui.label("Texture:");
                                                 texture_editor(
                                                     ui,
-                                                    &mut sphere.material.texture,
+                                                    sphere.material.texture_mut(),
                                                     &mut scene_changed,
                                                     ctx,
                                                     &mut self.image_previews,
+                                                    &scene_dir,
                                                 );
                                             });
                                             material_editor(
@@ -1256,10 +1966,11 @@ impl eframe::App for SceneEditorApp {
                                                 ui.label("Texture:");
                                                 texture_editor(
                                                     ui,
-                                                    &mut plane.material.texture,
+                                                    plane.material.texture_mut(),
                                                     &mut scene_changed,
                                                     ctx,
                                                     &mut self.image_previews,
+                                                    &scene_dir,
                                                 );
                                             });
                                             material_editor(
@@ -1288,10 +1999,11 @@ impl eframe::App for SceneEditorApp {
                                                 ui.label("Texture:");
                                                 texture_editor(
                                                     ui,
-                                                    &mut cube.material.texture,
+                                                    cube.material.texture_mut(),
                                                     &mut scene_changed,
                                                     ctx,
                                                     &mut self.image_previews,
+                                                    &scene_dir,
                                                 );
                                             });
                                             material_editor(
@@ -1327,10 +2039,11 @@ impl eframe::App for SceneEditorApp {
                                                 ui.label("Texture:");
                                                 texture_editor(
                                                     ui,
-                                                    &mut cylinder.material.texture,
+                                                    cylinder.material.texture_mut(),
                                                     &mut scene_changed,
                                                     ctx,
                                                     &mut self.image_previews,
+                                                    &scene_dir,
                                                 );
                                             });
                                             material_editor(
@@ -1339,7 +2052,71 @@ impl eframe::App for SceneEditorApp {
                                                 &mut scene_changed,
                                             );
                                         }
+                                        ObjectData::Mesh(mesh) => {
+                                            ui.label("OBJ File:");
+                                            ui.horizontal(|ui| {
+                                                scene_changed |= ui
+                                                    .add_sized(
+                                                        [200.0, 20.0],
+                                                        egui::TextEdit::singleline(&mut mesh.path),
+                                                    )
+                                                    .changed();
+                                                if ui.button("Browse...").clicked() {
+                                                    if let Some(new_path) = FileDialog::new()
+                                                        .add_filter("Wavefront OBJ", &["obj"])
+                                                        .pick_file()
+                                                    {
+                                                        mesh.path =
+                                                            relative_path(&new_path, &scene_dir);
+                                                        scene_changed = true;
+                                                    }
+                                                }
+                                            });
+                                            if let Some(None) = self.mesh_previews.get(&mesh.path) {
+                                                ui.colored_label(
+                                                    egui::Color32::RED,
+                                                    "Could not load OBJ file",
+                                                );
+                                            }
+                                            point3_editor(
+                                                ui,
+                                                "Position:",
+                                                &mut mesh.position,
+                                                &mut scene_changed,
+                                            );
+                                            ui.horizontal(|ui| {
+                                                ui.label("Scale:");
+                                                scene_changed |= ui
+                                                    .add(
+                                                        egui::DragValue::new(&mut mesh.scale)
+                                                            .speed(0.01),
+                                                    )
+                                                    .changed();
+                                            });
+                                            ui.group(|ui| {
+                                                ui.label("Texture:");
+                                                texture_editor(
+                                                    ui,
+                                                    mesh.material.texture_mut(),
+                                                    &mut scene_changed,
+                                                    ctx,
+                                                    &mut self.image_previews,
+                                                    &scene_dir,
+                                                );
+                                            });
+                                            material_editor(
+                                                ui,
+                                                &mut mesh.material,
+                                                &mut scene_changed,
+                                            );
+                                        }
                                     }
+
+                                    transform_editor(
+                                        ui,
+                                        object.transform_mut(),
+                                        &mut scene_changed,
+                                    );
                                 });
                             });
                         }
@@ -1374,11 +2151,167 @@ impl eframe::App for SceneEditorApp {
                                     .push(ObjectData::Cylinder(CylinderData::default()));
                                 scene_changed = true;
                             }
+                            if ui.button("Add Mesh").clicked() {
+                                self.scene_data
+                                    .objects
+                                    .push(ObjectData::Mesh(MeshData::default()));
+                                scene_changed = true;
+                            }
+                        });
+                    });
+
+                    ui.separator();
+
+                    // Lights Editor
+                    ui.collapsing("Lights", |ui| {
+                        let mut light_to_remove = None;
+                        for (i, light) in self.scene_data.lights.iter_mut().enumerate() {
+                            ui.push_id(i, |ui| {
+                                ui.group(|ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.heading(format!("Light {}", i));
+                                        if ui.button("Remove").clicked() {
+                                            light_to_remove = Some(i);
+                                            scene_changed = true;
+                                        }
+                                    });
+
+                                    let mut current_light_type = match light {
+                                        LightData::Point(_) => "Point",
+                                        LightData::Spot(_) => "Spot",
+                                        LightData::Directional(_) => "Directional",
+                                    };
+
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .radio_value(&mut current_light_type, "Point", "Point")
+                                            .changed()
+                                        {
+                                            *light = LightData::Point(PointLightData::default());
+                                            scene_changed = true;
+                                        }
+                                        if ui
+                                            .radio_value(&mut current_light_type, "Spot", "Spot")
+                                            .changed()
+                                        {
+                                            *light = LightData::Spot(SpotLightData::default());
+                                            scene_changed = true;
+                                        }
+                                        if ui
+                                            .radio_value(
+                                                &mut current_light_type,
+                                                "Directional",
+                                                "Directional",
+                                            )
+                                            .changed()
+                                        {
+                                            *light = LightData::Directional(
+                                                DirectionalLightData::default(),
+                                            );
+                                            scene_changed = true;
+                                        }
+                                    });
+
+                                    let (color, intensity) = match light {
+                                        LightData::Point(point) => {
+                                            point3_editor(
+                                                ui,
+                                                "Position:",
+                                                &mut point.position,
+                                                &mut scene_changed,
+                                            );
+                                            (&mut point.color, &mut point.intensity)
+                                        }
+                                        LightData::Spot(spot) => {
+                                            point3_editor(
+                                                ui,
+                                                "Position:",
+                                                &mut spot.position,
+                                                &mut scene_changed,
+                                            );
+                                            vec3_editor(
+                                                ui,
+                                                "Direction:",
+                                                &mut spot.direction,
+                                                &mut scene_changed,
+                                            );
+                                            ui.horizontal(|ui| {
+                                                ui.label("Angle:");
+                                                scene_changed |= ui
+                                                    .add(
+                                                        egui::DragValue::new(&mut spot.angle)
+                                                            .speed(0.5)
+                                                            .range(0.0..=90.0),
+                                                    )
+                                                    .changed();
+                                                ui.label("Falloff:");
+                                                scene_changed |= ui
+                                                    .add(
+                                                        egui::DragValue::new(&mut spot.falloff)
+                                                            .speed(0.01)
+                                                            .range(0.0..=1.0),
+                                                    )
+                                                    .changed();
+                                            });
+                                            (&mut spot.color, &mut spot.intensity)
+                                        }
+                                        LightData::Directional(directional) => {
+                                            vec3_editor(
+                                                ui,
+                                                "Direction:",
+                                                &mut directional.direction,
+                                                &mut scene_changed,
+                                            );
+                                            (&mut directional.color, &mut directional.intensity)
+                                        }
+                                    };
+
+                                    color_editor(ui, "Color:", color, &mut scene_changed);
+                                    ui.horizontal(|ui| {
+                                        ui.label("Intensity:");
+                                        scene_changed |= ui
+                                            .add(
+                                                egui::DragValue::new(intensity)
+                                                    .speed(1.0)
+                                                    .range(0.0..=f32::MAX),
+                                            )
+                                            .changed();
+                                    });
+                                });
+                            });
+                        }
+
+                        if let Some(i) = light_to_remove {
+                            self.scene_data.lights.remove(i);
+                            scene_changed = true;
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Add Point Light").clicked() {
+                                self.scene_data
+                                    .lights
+                                    .push(LightData::Point(PointLightData::default()));
+                                scene_changed = true;
+                            }
+                            if ui.button("Add Spot Light").clicked() {
+                                self.scene_data
+                                    .lights
+                                    .push(LightData::Spot(SpotLightData::default()));
+                                scene_changed = true;
+                            }
+                            if ui.button("Add Directional Light").clicked() {
+                                self.scene_data
+                                    .lights
+                                    .push(LightData::Directional(DirectionalLightData::default()));
+                                scene_changed = true;
+                            }
                         });
                     });
                 });
             });
 
+        self.load_mesh_previews();
+
         egui::CentralPanel::default().show(ctx, |ui| {
             match self.current_view {
                 ViewType::ThreeD => self.draw_scene_3d(ui),
@@ -0,0 +1,76 @@
+use crate::core::ray::Ray;
+use crate::core::vec3::{Point3, Vec3};
+
+/// Axis-aligned bounding box, used to cull rays before testing the objects inside.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(min: Point3, max: Point3) -> Self {
+        Self { min, max }
+    }
+
+    /// Build a box from two arbitrary corners, sorting each axis.
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        Self {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    /// Smallest box enclosing both boxes.
+    pub fn surrounding(a: Aabb, b: Aabb) -> Self {
+        Self {
+            min: Point3::new(a.min.x.min(b.min.x), a.min.y.min(b.min.y), a.min.z.min(b.min.z)),
+            max: Point3::new(a.max.x.max(b.max.x), a.max.y.max(b.max.y), a.max.z.max(b.max.z)),
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn extent(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Index of the axis (0 = x, 1 = y, 2 = z) along which the box is longest.
+    pub fn longest_axis(&self) -> usize {
+        let e = self.extent();
+        if e.x > e.y && e.x > e.z {
+            0
+        } else if e.y > e.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Slab test: does the ray pass through the box within [t_min, t_max]?
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let mut t_enter = t_min;
+        let mut t_exit = t_max;
+
+        for i in 0..3 {
+            let inv_d = 1.0 / ray.direction()[i];
+            let mut t0 = (self.min[i] - ray.origin()[i]) * inv_d;
+            let mut t1 = (self.max[i] - ray.origin()[i]) * inv_d;
+
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_enter = t_enter.max(t0);
+            t_exit = t_exit.min(t1);
+
+            if t_exit < t_enter {
+                return false;
+            }
+        }
+
+        true
+    }
+}
@@ -324,6 +324,15 @@ impl Color {
         }
     }
 
+    pub fn max_component(self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// Perceived brightness, weighting the channels as the eye does (Rec. 709).
+    pub fn luminance(self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn to_u8_tuple(self, apply_gamma: bool) -> (u8, u8, u8) {
         let gamma_correct = |c: f32| {
             let c = if apply_gamma { c.powf(1.0 / 2.2) } else { c };
@@ -1,28 +1,31 @@
+use crate::core::aabb::Aabb;
 use crate::core::color::Color;
 use crate::core::ray::Ray;
 use crate::core::vec3::{Point3, Vec3};
-use crate::material::material::Material;
+use crate::material::Scatter;
 
 /// Stores data about a ray-object intersection.
+///
+/// The material is borrowed from the object that was hit instead of being
+/// shared or copied, and its texture is only looked up once the hit is known
+/// to be the closest one and the material is asked to scatter.
 #[derive(Clone)]
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     /// Point of intersection
     pub p: Point3,
     /// Surface normal at intersection
     pub normal: Vec3,
     /// Ray parameter at intersection (distance along ray)
     pub t: f32,
-    /// Color from texture/material at the hit point
-    pub color: Color,
     /// Texture coordinate u
     pub u: f32,
     /// Texture coordinate v
     pub v: f32,
     pub front_face: bool,
-    pub material: Material,
+    pub material: &'a dyn Scatter,
 }
 
-impl HitRecord {
+impl HitRecord<'_> {
     pub fn face_normal(ray: &Ray, outward_normal: Vec3) -> (Vec3, bool) {
         let front_face = ray.direction().dot(outward_normal) < 0.0;
         let normal = if front_face {
@@ -34,6 +37,32 @@ impl HitRecord {
     }
 }
 
+/// A point sampled on the surface of a light source.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// Sampled point on the surface
+    pub p: Point3,
+    /// Outward surface normal at the sampled point
+    pub normal: Vec3,
+    /// Probability density of the sample with respect to surface area
+    pub pdf: f32,
+    /// Light emitted from the sampled point
+    pub emission: Color,
+}
+
 pub trait Hittable: Send + Sync {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>;
+
+    /// World-space box enclosing the object, used to build the scene BVH.
+    fn bounding_box(&self) -> Aabb;
+
+    /// Whether the object emits light and should be sampled directly.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Sample a point on the surface as seen from `origin`, for direct light sampling.
+    fn sample_surface(&self, _origin: Point3) -> Option<LightSample> {
+        None
+    }
 }
@@ -1,9 +1,13 @@
+pub mod aabb;
 pub mod color;
 pub mod hit;
 pub mod ray;
+pub mod sampler;
 pub mod vec3;
 
+pub use aabb::Aabb;
 pub use color::Color;
-pub use hit::{HitRecord, Hittable};
+pub use hit::{HitRecord, Hittable, LightSample};
 pub use ray::Ray;
+pub use sampler::Sampler;
 pub use vec3::{Point3, Vec3};
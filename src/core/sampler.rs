@@ -0,0 +1,294 @@
+use std::cell::Cell;
+
+/// Where the random numbers of each sample come from.
+///
+/// Every call to `random_float` while a sample is being traced takes the next
+/// dimension of that sample: the first two jitter the position in the pixel,
+/// the ones after go to the lens, the surfaces and the lights along the path.
+/// The low discrepancy samplers spread each dimension more evenly over a
+/// pixel's samples than independent numbers do, so images converge with
+/// fewer samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Sampler {
+    /// Independent uniform random numbers
+    #[default]
+    Independent,
+    /// Every dimension is split into as many strata as the pixel takes
+    /// samples, and each sample lands in a different one
+    Stratified,
+    /// Halton sequence, shifted by a different random offset in every pixel.
+    /// Dimensions past the first 64 use independent numbers
+    Halton,
+    /// Sobol sequence with Owen scrambling, best with power of two sample counts
+    Sobol,
+}
+
+/// The sample the current thread is tracing, and how many numbers it has used.
+#[derive(Debug, Clone, Copy)]
+struct SampleState {
+    sampler: Sampler,
+    seed: u64,
+    index: u32,
+    count: u32,
+    dimension: u32,
+}
+
+thread_local! {
+    static SAMPLE: Cell<Option<SampleState>> = const { Cell::new(None) };
+}
+
+impl Sampler {
+    /// Make `random_float` on this thread give the numbers of sample `index`
+    /// of the pixel until `end_sample` is called. `seed` must be the same for
+    /// every sample of a pixel and differ between pixels; `count` is the number
+    /// of samples the pixel is meant to get.
+    pub fn begin_sample(self, seed: u64, index: u32, count: u32) {
+        let state = match self {
+            Sampler::Independent => None,
+            sampler => Some(SampleState {
+                sampler,
+                seed,
+                index,
+                count: count.max(1),
+                dimension: 0,
+            }),
+        };
+        SAMPLE.with(|sample| sample.set(state));
+    }
+}
+
+/// Go back to independent random numbers on this thread.
+pub fn end_sample() {
+    SAMPLE.with(|sample| sample.set(None));
+}
+
+/// Next number of the current sample, or `None` when independent numbers should be used.
+pub(crate) fn next_value() -> Option<f32> {
+    SAMPLE.with(|sample| {
+        let mut state = sample.get()?;
+        let value = state.value();
+        state.dimension += 1;
+        sample.set(Some(state));
+        value
+    })
+}
+
+impl SampleState {
+    fn value(&self) -> Option<f32> {
+        let dimension = self.dimension as u64;
+        match self.sampler {
+            Sampler::Independent => None,
+            Sampler::Stratified => {
+                // Start a fresh shuffle of the strata once the pixel has had them all
+                let round = (self.index / self.count) as u64;
+                let permutation = hash(self.seed, dimension, round);
+                let stratum = permute(self.index % self.count, self.count, permutation as u32);
+                // Each sample gets its own offset in its stratum, or the strata
+                // would all be sampled at the same spot, like a regular grid
+                let jitter_tag = (round ^ STRATUM_JITTER) ^ ((self.index as u64) << 32);
+                let jitter = unit_float(hash(self.seed, dimension, jitter_tag) as u32);
+                Some(((stratum as f32 + jitter) / self.count as f32).min(ONE_MINUS_EPSILON))
+            }
+            Sampler::Halton => {
+                let base = *PRIMES.get(self.dimension as usize)?;
+                let offset = unit_float(hash(self.seed, dimension, HALTON_OFFSET) as u32);
+                let value = radical_inverse(base, self.index) + offset;
+                Some((value - value.floor()).min(ONE_MINUS_EPSILON))
+            }
+            Sampler::Sobol => {
+                // Pairs of dimensions take the first two Sobol dimensions, each
+                // pair with its samples in a different order
+                let pair = dimension / 2;
+                let index = owen_scramble(self.index, hash(self.seed, pair, SOBOL_SHUFFLE) as u32);
+                let bits = if dimension.is_multiple_of(2) {
+                    index.reverse_bits()
+                } else {
+                    sobol_second_dimension(index)
+                };
+                let bits = owen_scramble(bits, hash(self.seed, dimension, SOBOL_SCRAMBLE) as u32);
+                Some(unit_float(bits))
+            }
+        }
+    }
+}
+
+/// Largest `f32` below 1.
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+// Tags keeping the hashes drawn for different purposes apart
+const STRATUM_JITTER: u64 = 0x5354_5241_5441;
+const HALTON_OFFSET: u64 = 0x4841_4c54_4f4e;
+const SOBOL_SHUFFLE: u64 = 0x5348_5546;
+const SOBOL_SCRAMBLE: u64 = 0x5343_524d;
+
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+/// `index` written in `base` and mirrored around the decimal point.
+fn radical_inverse(base: u32, mut index: u32) -> f32 {
+    let inverse_base = 1.0 / base as f64;
+    let mut factor = inverse_base;
+    let mut value = 0.0;
+    while index > 0 {
+        value += (index % base) as f64 * factor;
+        index /= base;
+        factor *= inverse_base;
+    }
+    value as f32
+}
+
+/// Second dimension of the Sobol sequence, as the binary digits of a fraction.
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1u32 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+/// Randomly permute the binary digits of `value` as a fraction, each digit
+/// depending on the ones before it (Burley, "Practical Hash-based Owen Scrambling").
+fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut x = value.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+/// Position of `index` in a random permutation of `0..len` picked by `seed`
+/// (Kensler, "Correlated Multi-Jittered Sampling").
+fn permute(mut index: u32, len: u32, seed: u32) -> u32 {
+    let mut mask = len - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170_893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929_eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935_fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dc_b303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e50_1cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860_a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < len {
+            return index.wrapping_add(seed) % len;
+        }
+    }
+}
+
+/// The top 24 bits of `bits` as a number in [0, 1).
+fn unit_float(bits: u32) -> f32 {
+    (bits >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn hash(seed: u64, a: u64, b: u64) -> u64 {
+    let mut z = seed ^ a.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ b.rotate_left(32);
+    // SplitMix64 finalizer
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The first `dimensions` numbers of every sample of a pixel, by dimension.
+    fn pixel_values(
+        sampler: Sampler,
+        seed: u64,
+        samples: u32,
+        count: u32,
+        dimensions: usize,
+    ) -> Vec<Vec<f32>> {
+        let mut values = vec![Vec::new(); dimensions];
+        for index in 0..samples {
+            sampler.begin_sample(seed, index, count);
+            for dimension in values.iter_mut() {
+                dimension.push(next_value().unwrap());
+            }
+            end_sample();
+        }
+        values
+    }
+
+    #[test]
+    fn stratified_fills_every_stratum_once_per_round() {
+        for count in [1, 2, 7, 16, 33] {
+            let rounds = 3;
+            let values = pixel_values(Sampler::Stratified, 42, count * rounds, count, 6);
+            for (dimension, values) in values.iter().enumerate() {
+                for round in values.chunks(count as usize) {
+                    let mut strata: Vec<u32> =
+                        round.iter().map(|&v| (v * count as f32) as u32).collect();
+                    strata.sort_unstable();
+                    let expected: Vec<u32> = (0..count).collect();
+                    assert_eq!(strata, expected, "count {count}, dimension {dimension}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stratified_jitters_each_sample_within_its_stratum() {
+        let count = 16;
+        let values = pixel_values(Sampler::Stratified, 7, count * 2, count, 4);
+        for (dimension, values) in values.iter().enumerate() {
+            let mut offsets: Vec<f32> = values
+                .iter()
+                .map(|&v| v * count as f32 - (v * count as f32).floor())
+                .collect();
+            offsets.sort_unstable_by(f32::total_cmp);
+            offsets.dedup();
+            assert_eq!(
+                offsets.len(),
+                values.len(),
+                "dimension {dimension}: repeated offsets"
+            );
+
+            // Offsets should cover the stratum, not cluster at one spot
+            let spread = offsets.last().unwrap() - offsets.first().unwrap();
+            assert!(
+                spread > 0.5,
+                "dimension {dimension}: offsets span only {spread}"
+            );
+        }
+    }
+
+    #[test]
+    fn low_discrepancy_values_are_in_range_and_repeatable() {
+        for sampler in [Sampler::Stratified, Sampler::Halton, Sampler::Sobol] {
+            let values = pixel_values(sampler, 3, 64, 64, 64);
+            assert_eq!(values, pixel_values(sampler, 3, 64, 64, 64), "{sampler:?}");
+            assert_ne!(values, pixel_values(sampler, 4, 64, 64, 64), "{sampler:?}");
+            for value in values.iter().flatten() {
+                assert!((0.0..1.0).contains(value), "{sampler:?}: {value}");
+            }
+        }
+    }
+}
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Result};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
-use rand::Rng;
+use crate::{random_float, random_range};
 
 use crate::square;
 
@@ -83,37 +83,47 @@ impl Vec3 {
     }
 
     pub fn random_unit_vector() -> Vec3 {
-        let mut rng = rand::rng();
-        let a: f32 = rng.random_range(0.0..2.0 * std::f32::consts::PI);
-        let z: f32 = rng.random_range(-1.0..1.0);
+        let a: f32 = random_range(0.0..2.0 * std::f32::consts::PI);
+        let z: f32 = random_range(-1.0..1.0);
         let r = (1.0 - z * z).sqrt();
         Vec3::new(r * a.cos(), r * a.sin(), z)
     }
 
+    /// Uniform point in the unit ball. Always takes three random numbers, so
+    /// samplers can keep the dimensions of every sample lined up.
     pub fn random_in_unit_sphere() -> Vec3 {
-        let mut rng = rand::rng();
-
-        loop {
-            let x: f32 = rng.random_range(-1.0..1.0);
-            let y: f32 = rng.random_range(-1.0..1.0);
-            let z: f32 = rng.random_range(-1.0..1.0);
-
-            let point = Vec3::new(x, y, z);
-            if point.length_squared() < 1.0 {
-                return point;
-            }
-        }
+        let direction = Vec3::random_unit_vector();
+        direction * random_float().cbrt()
     }
 
+    /// Uniform direction on the side of the unit sphere `normal` points to.
     pub fn random_in_hemisphere(normal: Vec3) -> Vec3 {
-        let in_unit_sphere = Vec3::random_in_unit_sphere();
-        if in_unit_sphere.dot(normal) > 0.0 {
-            in_unit_sphere
+        let direction = Vec3::random_unit_vector();
+        if direction.dot(normal) > 0.0 {
+            direction
         } else {
-            -in_unit_sphere
+            -direction
         }
     }
 
+    /// Direction on the side of the unit sphere `normal` points to, picked with
+    /// a density of cos θ / π, θ being its angle to the normal.
+    pub fn random_cosine_direction(normal: Vec3) -> Vec3 {
+        let (tangent, bitangent) = normal.tangent_frame();
+        let r = random_float().sqrt();
+        let phi = 2.0 * std::f32::consts::PI * random_float();
+        let z = (1.0 - r * r).max(0.0).sqrt();
+        tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * z
+    }
+
+    /// Two unit vectors that, with this (unit) vector, form an orthonormal basis.
+    pub fn tangent_frame(&self) -> (Vec3, Vec3) {
+        let helper = if self.x.abs() > 0.9 { Vec3::Y } else { Vec3::X };
+        let tangent = self.cross(helper).normalize();
+        let bitangent = self.cross(tangent);
+        (tangent, bitangent)
+    }
+
     pub fn reflect(&self, normal: Vec3) -> Vec3 {
         *self - 2.0 * self.dot(normal) * normal
     }
@@ -134,6 +144,12 @@ impl From<Vec3> for glam::Vec3 {
     }
 }
 
+impl From<glam::Vec3> for Vec3 {
+    fn from(v: glam::Vec3) -> Self {
+        Vec3::new(v.x, v.y, v.z)
+    }
+}
+
 impl Display for Vec3 {
     fn fmt(&self, f: &mut Formatter) -> Result {
         write!(f, "{} {} {}", self.x, self.y, self.z)
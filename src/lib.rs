@@ -4,13 +4,39 @@ pub mod objects;
 pub mod particle_system;
 pub mod pixels;
 pub mod scene;
+pub mod scenes;
 
 use std::ops::Mul;
 pub fn square<T: Mul<Output = T> + Copy>(value: T) -> T {
     value * value
 }
 
-use rand::Rng;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::ops::Range;
+
+thread_local! {
+    /// Generator behind every random helper in the crate. Seeded from the OS
+    /// until `seed_rng` is called, so renders are only repeatable when the
+    /// caller seeds it.
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_rng(&mut rand::rng()));
+}
+
+/// Restart the current thread's random sequence from `seed`.
+pub fn seed_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+/// Uniform number in [0, 1). While a sample is being traced with a
+/// `Sampler` other than `Independent`, it is the sample's next dimension.
 pub fn random_float() -> f32 {
-    rand::rng().random::<f32>()
+    core::sampler::next_value().unwrap_or_else(|| RNG.with(|rng| rng.borrow_mut().random::<f32>()))
+}
+
+pub fn random_range(range: Range<f32>) -> f32 {
+    match core::sampler::next_value() {
+        Some(value) => range.start + (range.end - range.start) * value,
+        None => RNG.with(|rng| rng.borrow_mut().random_range(range)),
+    }
 }
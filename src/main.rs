@@ -1,41 +1,170 @@
-use clap::Parser;
-use rt_2::scene::Scene;
-use scenes::*;
-
-mod scenes;
+use clap::{Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use rt_2::core::Sampler;
+use rt_2::pixels::{Image, SampleBuffer, ToneMapping};
+use rt_2::scene::storage::{RenderData, SceneData, SceneLoader, SCENE_VERSION};
+use rt_2::scene::{Adaptive, Checkpoint, Progressive, Scene, SceneHasher};
+use rt_2::scenes::*;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Scene number to render (1-4), or 0 to load from file
     #[arg(short = 's', long = "scene", default_value = "3")]
     scene: String,
 
-    /// Output filename
-    #[arg(short = 'o', long = "output", default_value = "output.ppm")]
-    output: String,
+    /// Output filename; the extension selects PPM, PNG, JPEG, EXR or HDR [default: output.ppm]
+    #[arg(short = 'o', long = "output")]
+    output: Option<String>,
 
     /// Resolution width and height
     #[arg(short = 'r', long = "resolution", value_names = &["WIDTH", "HEIGHT"])]
     resolution: Option<Vec<u32>>,
 
-    /// Samples per pixel
-    #[arg(short = 'q', long = "quality", default_value_t = 32)]
-    samples: u32,
+    /// Samples per pixel [default: 32]
+    #[arg(short = 'q', long = "quality")]
+    samples: Option<u32>,
 
-    /// Depth per pixel
-    #[arg(short = 'd', long = "depth", default_value_t = 10)]
-    depth: u32,
+    /// Depth per pixel [default: 10]
+    #[arg(short = 'd', long = "depth")]
+    depth: Option<u32>,
+
+    /// Bounces every path takes before Russian roulette may end it [default: 3]
+    #[arg(long = "roulette-depth", value_name = "BOUNCES")]
+    roulette_depth: Option<u32>,
 
     /// Disable parallelization
     #[arg(short = 'n', long = "non-parallelized")]
     non_parallelized: bool,
 
+    /// Seed for the random numbers; a random seed is chosen and printed when omitted
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+
+    /// Where the random numbers of each sample come from [default: independent]
+    #[arg(long = "sampler", value_enum)]
+    sampler: Option<SamplerArg>,
+
+    /// Curve compressing bright light into the displayable range [default: none]
+    #[arg(long = "tone-mapping", value_enum)]
+    tone_mapping: Option<ToneMappingArg>,
+
+    /// Brightness adjustment in stops; each +1 doubles the light [default: 0]
+    #[arg(long = "exposure", allow_negative_numbers = true)]
+    exposure: Option<f32>,
+
+    /// Render in passes over the whole image, saving the image so far as it goes
+    #[arg(long = "progressive")]
+    progressive: bool,
+
+    /// Stop a progressive render after this many seconds; implies --progressive
+    #[arg(long = "time-limit", value_name = "SECONDS", value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
+
+    /// Save the image every N passes of a progressive render
+    #[arg(long = "save-every", value_name = "PASSES")]
+    save_every: Option<u32>,
+
+    /// Save the image every N seconds of a progressive render [default: 30, unless --save-every is given]
+    #[arg(long = "save-interval", value_name = "SECONDS", value_parser = parse_seconds)]
+    save_interval: Option<Duration>,
+
+    /// Save the samples of a progressive render to FILE along with the image; implies --progressive
+    #[arg(long = "checkpoint", value_name = "FILE")]
+    checkpoint: Option<String>,
+
+    /// Continue the render saved in the --checkpoint file
+    #[arg(long = "resume", requires = "checkpoint")]
+    resume: bool,
+
+    /// Stop sampling each pixel once its estimated error is below THRESHOLD, taking at most -q samples
+    #[arg(
+        long = "adaptive",
+        value_name = "THRESHOLD",
+        num_args = 0..=1,
+        default_missing_value = "0.01"
+    )]
+    adaptive: Option<f32>,
+
+    /// Samples every pixel takes before adaptive sampling judges its noise [default: 16]
+    #[arg(long = "min-samples", value_name = "N", requires = "adaptive")]
+    min_samples: Option<u32>,
+
+    /// Save an image of how many samples each pixel took to FILE
+    #[arg(long = "heatmap", value_name = "FILE")]
+    heatmap: Option<String>,
+
+    /// Check the scene file for problems and exit without rendering
+    #[arg(long = "check")]
+    check: bool,
+
     /// Info
     #[arg(short = 'i', long = "info")]
     info: bool,
 }
 
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let seconds: f64 = arg.parse().map_err(|e| format!("{e}"))?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("{e}"))
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SamplerArg {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl From<SamplerArg> for Sampler {
+    fn from(arg: SamplerArg) -> Self {
+        match arg {
+            SamplerArg::Independent => Sampler::Independent,
+            SamplerArg::Stratified => Sampler::Stratified,
+            SamplerArg::Halton => Sampler::Halton,
+            SamplerArg::Sobol => Sampler::Sobol,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ToneMappingArg {
+    None,
+    Reinhard,
+    Aces,
+}
+
+impl From<ToneMappingArg> for ToneMapping {
+    fn from(arg: ToneMappingArg) -> Self {
+        match arg {
+            ToneMappingArg::None => ToneMapping::None,
+            ToneMappingArg::Reinhard => ToneMapping::Reinhard,
+            ToneMappingArg::Aces => ToneMapping::Aces,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Rewrite scene files in the latest scene format
+    Migrate {
+        /// Scene JSON files to upgrade in place
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Report what would change without writing the files
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+    },
+}
+
 fn show_info() {
     println!("    Scene number or filename to render flag:
     Shorthand: -s, Full: -scene, Default value: 3
@@ -46,9 +175,10 @@ fn show_info() {
     cargo run --bin scene_editor
 
     Output filename flag:
-    Shorthand: -o, Full: -output, Default value: output.ppm
-    Example: cargo run -- -o my_render.ppm
-    Purpose: Specify the output filename
+    Shorthand: -o, Full: -output, Default value: output.ppm, or the scene file's render output
+    Example: cargo run -- -o my_render.png
+    Purpose: Specify the output filename. The extension selects the format:
+    .ppm, .png, .jpg/.jpeg, or .exr/.hdr to keep unclamped linear colors
 
     Resolution width and height flag:
     Shorthand: -r, Full: -resolution, Default value is set individually for each scene
@@ -56,12 +186,12 @@ fn show_info() {
     Purpose: Specify the resolution width and height
 
     Samples per pixel flag:
-    Shorthand: -q, Full: -quality, Default value: 32
+    Shorthand: -q, Full: -quality, Default value: 32, or the scene file's render samples
     Example: cargo run -- -q 128
     Purpose: Specify the samples per pixel
 
     Depth per pixel flag:
-    Shorthand: -d, Full: -depth, Default value: 10
+    Shorthand: -d, Full: -depth, Default value: 10, or the scene file's render max_depth
     Example: cargo run -- -d 8
     Purpose: Specify the depth per pixel
 
@@ -70,35 +200,250 @@ fn show_info() {
     Example: cargo run -- -n
     Purpose: Disable parallelization, used for single-threaded rendering, typically for running the program without over-stressing your cpu
 
+    Roulette depth flag:
+    Full: --roulette-depth, Default value: 3, or the scene file's render roulette_depth
+    Example: cargo run -- -s 4 -d 50 --roulette-depth 5
+    Purpose: Bounces every path takes before Russian roulette may end it. After that, paths
+    carrying little light are stopped at random and the rest weighted up to make up for
+    them, so a high -d costs little more than a low one. -d or more turns roulette off
+
+    Seed flag:
+    Full: --seed, Default value: the scene file's render seed, or random (printed at startup)
+    Example: cargo run -- --seed 42
+    Purpose: Make renders repeatable; the same scene, settings and seed always give the same image
+
+    Sampler flag:
+    Full: --sampler, Default value: independent, or the scene file's render sampler
+    Example: cargo run -- -q 64 --sampler sobol
+    Purpose: Where the random numbers of each sample come from: independent, stratified,
+    halton or sobol. The last three spread the samples of a pixel more evenly, so the image
+    is cleaner at the same sample count. Sobol works best with power of two sample counts
+
+    Tone mapping flag:
+    Full: --tone-mapping, Default value: none, or the scene file's render tone_mapping
+    Example: cargo run -- --tone-mapping aces
+    Purpose: Compress bright light into the displayable range: none (clip), reinhard or aces
+
+    Exposure flag:
+    Full: --exposure, Default value: 0, or the scene file's render exposure
+    Example: cargo run -- --exposure -1.5
+    Purpose: Brighten or darken the image in stops; each +1 doubles the light
+
+    Progressive flag:
+    Full: --progressive
+    Example: cargo run -- -q 1000 --progressive
+    Purpose: Render in passes of one sample per pixel until the sample count is reached,
+    saving the image so far every 30 seconds so an interrupted render still leaves a result
+
+    Time limit flag:
+    Full: --time-limit, Default value: none
+    Example: cargo run -- -q 10000 --time-limit 3600
+    Purpose: Stop a progressive render after the pass that ends past the given number of
+    seconds, keeping the samples taken so far. Implies --progressive
+
+    Save flags:
+    Full: --save-every, --save-interval, Default value: every 30 seconds
+    Example: cargo run -- --progressive --save-every 16
+    Purpose: How often a progressive render saves the image so far, in passes or in seconds
+
+    Checkpoint flags:
+    Full: --checkpoint, --resume
+    Example: cargo run -- -s scene5.json -q 5000 --checkpoint scene5.ckpt --resume
+    Purpose: --checkpoint saves the samples of a progressive render to a file each time the
+    image is saved and when it ends. --resume continues from that file, refusing to if the
+    scene or the seed, depth, sampler or resolution have changed. Resuming with a higher -q
+    adds samples to a finished render
+
+    Adaptive flags:
+    Full: --adaptive [THRESHOLD], --min-samples, Default value: off; 0.01 and 16 when on
+    Example: cargo run -- -s 5 -q 1024 --adaptive 0.005 --heatmap samples.png
+    Purpose: Give each pixel --min-samples samples, then keep sampling only the pixels whose
+    estimated error, on the 0 to 1 scale of the saved image, is above the threshold, up to
+    -q samples. Flat regions finish early while glass and soft shadows get the samples
+
+    Heatmap flag:
+    Full: --heatmap, Default value: none
+    Example: cargo run -- --adaptive --heatmap samples.png
+    Purpose: Save an image of how many samples each pixel took, running from black through
+    blue, red and yellow to white at the most any pixel took, for tuning the adaptive threshold
+
+    Check flag:
+    Full: --check
+    Example: cargo run -- -s scene.json --check
+    Purpose: Validate a scene file without rendering it, listing every problem found
+    (missing files, negative sizes, infinite coordinates) with where it is in the file
+
     Info flag:
     Shorthand: -i, Full: -info
     Example: cargo run -- -i
     Purpose: Print the usage info
+
+    Migrate command:
+    Example: cargo run -- migrate scene1.json scene2.json
+    or: cargo run -- migrate --dry-run scene1.json
+    Purpose: Upgrade scene files written in older versions of the scene format,
+    printing what changed. Older files still load, but are upgraded on every load
     ")
 }
 
+/// Upgrade each scene file to the latest format, reporting what changed.
+fn migrate_files(files: &[String], dry_run: bool) -> std::io::Result<()> {
+    let mut failed = 0;
+    for file in files {
+        let result = fs::read_to_string(file).and_then(|json| {
+            SceneData::from_json_migrated(&json).map_err(std::io::Error::other)
+        });
+        let (scene_data, migration) = match result {
+            Ok(migrated) => migrated,
+            Err(e) => {
+                eprintln!("{file}: {e}");
+                failed += 1;
+                continue;
+            }
+        };
+
+        if !migration.upgraded() {
+            println!("{file}: already at version {SCENE_VERSION}");
+            continue;
+        }
+        println!(
+            "{file}: version {} -> {SCENE_VERSION}",
+            migration.from_version
+        );
+        for change in &migration.changes {
+            println!("  {change}");
+        }
+        if dry_run {
+            continue;
+        }
+
+        let json = serde_json::to_string_pretty(&scene_data).map_err(std::io::Error::other)?;
+        if let Err(e) = fs::write(file, json) {
+            eprintln!("{file}: {e}");
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(std::io::Error::other(format!(
+            "{failed} of {} files could not be migrated",
+            files.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Load a scene file as a render would, reporting what is wrong with it.
+fn check_scene(file: &str) -> std::io::Result<()> {
+    if file.parse::<u32>().is_ok() {
+        println!("Scene {file} is built in; only scene files can be checked");
+        return Ok(());
+    }
+
+    match Scene::load_from_file(file) {
+        Ok(_) => {
+            println!("{file}: OK");
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{file}: {e}");
+            Err(std::io::Error::other(format!("{file} cannot be rendered")))
+        }
+    }
+}
+
+/// Write to a hidden file next to `output` and then move it into place, so a
+/// render killed while saving never leaves a half-written image behind.
+fn save_image(image: &Image, output: &str) -> std::io::Result<()> {
+    let path = Path::new(output);
+    let Some(name) = path.file_name() else {
+        return image.save(output);
+    };
+    let temp = path.with_file_name(format!(".{}", name.to_string_lossy()));
+    image.save(&temp.to_string_lossy())?;
+    fs::rename(&temp, path)
+}
+
+/// Hash of what decides the samples of a render, apart from the seed: the
+/// scene with the contents of the images and meshes it uses, the maximum
+/// depth and the resolution. Render settings that only affect the finished
+/// image, such as the sample count, are left out.
+fn scene_hash(scene_arg: &str, loaded_from_file: bool, scene: &Scene) -> u64 {
+    let mut hasher = SceneHasher::default();
+    let scene_data = loaded_from_file
+        .then(|| SceneData::from_json(&fs::read_to_string(scene_arg).ok()?).ok())
+        .flatten();
+    match scene_data {
+        Some(mut scene_data) => {
+            scene_data.render = Default::default();
+            let json = serde_json::to_string(&scene_data).unwrap_or_default();
+            hasher.write(json.as_bytes());
+
+            let loader = SceneLoader::for_scene_file(scene_arg);
+            for path in scene_data.file_paths_mut() {
+                // The scene loaded, so every file could be read a moment ago
+                let contents = fs::read(loader.resolve(path)).unwrap_or_default();
+                hasher.write_usize(contents.len());
+                hasher.write(&contents);
+            }
+        }
+        // Built-in scenes are only known by number, so changes to their code
+        // or to the files they load go unnoticed
+        None => hasher.write(scene_arg.as_bytes()),
+    }
+    let (width, height) = scene.camera().resolution();
+    hasher.write_u32(scene.max_depth());
+    hasher.write_u32(scene.roulette_depth());
+    scene.sampler().hash(&mut hasher);
+    hasher.write_u32(width);
+    hasher.write_u32(height);
+    hasher.finish()
+}
+
 fn main() -> std::io::Result<()> {
     let args = Args::parse();
 
+    if let Some(Command::Migrate { files, dry_run }) = &args.command {
+        return migrate_files(files, *dry_run);
+    }
+
     // Handle info flag
     if args.info {
         show_info();
         return Ok(());
     }
 
+    if args.check {
+        return check_scene(&args.scene);
+    }
+
+    let resumed = match (&args.checkpoint, args.resume) {
+        (Some(path), true) => Some(Checkpoint::load(path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("could not resume from {path}: {e}"))
+        })?),
+        _ => None,
+    };
+
+    // Seed before building the scene so particle systems are placed repeatably too
+    let build_seed = args
+        .seed
+        .or(resumed.as_ref().map(|checkpoint| checkpoint.seed))
+        .unwrap_or_else(rand::random);
+    rt_2::seed_rng(build_seed);
+
     let scene_arg = args.scene.as_str();
-    let scenes = vec!["1", "2", "3", "4", "5", "6", "7", "8"];
+    let scenes = ["1", "2", "3", "4", "5", "6", "7", "8"];
+    let mut loaded_from_file = false;
     let mut scene = if !scenes.contains(&scene_arg) {
-        match Scene::load_from_file(&scene_arg) {
+        match Scene::load_from_file(scene_arg) {
             Ok(s) => {
                 println!("Loaded scene from {}.", scene_arg);
+                loaded_from_file = true;
                 s
             }
             Err(e) => {
-                eprintln!(
-                    "Could not load scene from {}: {}. Falling back to scene three.",
-                    scene_arg, e
-                );
+                eprintln!("Could not load scene from {}: {}", scene_arg, e);
+                eprintln!("Falling back to scene three.");
                 let mut s = Scene::new();
                 scene_three(&mut s);
                 s
@@ -132,9 +477,175 @@ fn main() -> std::io::Result<()> {
         }
     }
 
-    scene.set_sample_size(args.samples);
-    scene.set_max_depth(args.depth);
-    scene.render(&args.output, !args.non_parallelized)?;
+    // Scene files bring their own render settings, with the same defaults
+    if !loaded_from_file {
+        scene.set_sample_size(RenderData::DEFAULT_SAMPLES);
+        scene.set_max_depth(RenderData::DEFAULT_MAX_DEPTH);
+    }
+
+    // Flags override the render settings of a scene file
+    if let Some(samples) = args.samples {
+        scene.set_sample_size(samples);
+    }
+    if let Some(depth) = args.depth {
+        scene.set_max_depth(depth);
+    }
+    if let Some(roulette_depth) = args.roulette_depth {
+        scene.set_roulette_depth(roulette_depth);
+    }
+    if let Some(sampler) = args.sampler {
+        scene.set_sampler(sampler.into());
+    }
+    if let Some(tone_mapping) = args.tone_mapping {
+        scene.set_tone_mapping(tone_mapping.into());
+    }
+    if let Some(exposure) = args.exposure {
+        scene.set_exposure(exposure);
+    }
+    if let Some(threshold) = args.adaptive {
+        let defaults = Adaptive::default();
+        scene.set_adaptive(Some(Adaptive {
+            min_samples: args.min_samples.unwrap_or(defaults.min_samples),
+            threshold,
+        }));
+    }
+    let adaptive = scene.adaptive().is_some();
+    let seed = args.seed.or(scene.seed()).unwrap_or(build_seed);
+    println!("Seed: {seed}");
+    scene.set_seed(seed);
+
+    let output = args
+        .output
+        .clone()
+        .or_else(|| scene.output().map(String::from))
+        .unwrap_or_else(|| "output.ppm".to_string());
+
+    let parallelized = !args.non_parallelized;
+    let (width, height) = scene.camera().resolution();
+
+    // Adaptive renders leave pixels at different sample counts
+    let samples_taken = |buffer: &SampleBuffer| {
+        if adaptive {
+            format!("{:.1} samples per pixel on average", buffer.mean_samples())
+        } else {
+            format!("{} samples per pixel", buffer.min_samples())
+        }
+    };
+
+    let progressive = args.progressive || args.time_limit.is_some() || args.checkpoint.is_some();
+    let scene_hash = scene_hash(scene_arg, loaded_from_file, &scene);
+    if let Some(checkpoint) = &resumed {
+        let path = args.checkpoint.as_deref().unwrap_or_default();
+        if checkpoint.seed != seed {
+            return Err(std::io::Error::other(format!(
+                "{path} was rendered with seed {}, not {seed}",
+                checkpoint.seed
+            )));
+        }
+        let size = (checkpoint.buffer.width(), checkpoint.buffer.height());
+        if checkpoint.scene_hash != scene_hash || size != (width as usize, height as usize) {
+            return Err(std::io::Error::other(format!(
+                "{path} was rendered from a different scene, depth, sampler or resolution"
+            )));
+        }
+        println!(
+            "Resuming from {path} at {}",
+            samples_taken(&checkpoint.buffer)
+        );
+    }
+
+    // Create progress bar, counting rows, or samples per pixel in progressive mode,
+    // or finished pixels when sampling adaptively
+    let (length, unit) = if adaptive {
+        (width * height, "pixels")
+    } else if progressive {
+        (scene.sample_size(), "samples")
+    } else {
+        (height, "lines")
+    };
+    let prog_bar = ProgressBar::new(length as u64);
+    prog_bar.set_style(
+        ProgressStyle::with_template(&format!(
+            "{{spinner:.green}} [{{elapsed_precise}}] [{{wide_bar:.cyan/blue}}] {{pos:>3}}/{{len:3}} {unit} ({{percent}}%) {{eta}}"
+        ))
+        .unwrap()
+        .progress_chars("█▉▊▋▌▍▎▏  ")
+    );
+
+    println!("Starting render: {width}x{height} pixels");
+    if parallelized {
+        println!("Using parallelized rendering");
+    } else {
+        println!("Using single-threaded rendering");
+    }
+
+    let mut buffer = match resumed {
+        Some(checkpoint) => checkpoint.buffer,
+        None => SampleBuffer::new(width as usize, height as usize),
+    };
+    let image = if progressive {
+        let settings = Progressive {
+            time_limit: args.time_limit,
+            checkpoint_passes: args.save_every,
+            checkpoint_interval: args.save_interval.or(match args.save_every {
+                Some(_) => None,
+                None => Some(Duration::from_secs(30)),
+            }),
+            ..Progressive::default()
+        };
+        let save_checkpoint = |buffer: &SampleBuffer| match &args.checkpoint {
+            Some(path) => Checkpoint {
+                scene_hash,
+                seed,
+                buffer: buffer.clone(),
+            }
+            .save(path)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{path}: {e}"))),
+            None => Ok(()),
+        };
+
+        if !adaptive {
+            prog_bar.set_position(buffer.min_samples() as u64);
+        }
+        let started = Instant::now();
+        let image = scene.render_progressive(
+            &mut buffer,
+            &settings,
+            parallelized,
+            Some(&|samples, _| prog_bar.set_position(samples as u64)),
+            &mut |image, buffer| {
+                let saved = save_image(image, &output)
+                    .map_err(|e| std::io::Error::new(e.kind(), format!("{output}: {e}")))
+                    .and_then(|()| save_checkpoint(buffer));
+                prog_bar.suspend(|| match saved {
+                    Ok(()) => println!("Saved {output} at {}", samples_taken(buffer)),
+                    Err(e) => eprintln!("Could not save {e}"),
+                });
+            },
+        );
+        prog_bar.abandon();
+        save_checkpoint(&buffer)?;
+        if args.time_limit.is_some_and(|limit| started.elapsed() >= limit) {
+            println!("Time limit reached at {}", samples_taken(&buffer));
+        }
+        image
+    } else {
+        let image = scene.render_into(&mut buffer, parallelized, Some(&|done, _| {
+            prog_bar.set_position(done as u64)
+        }));
+        prog_bar.finish();
+        image
+    };
+    if adaptive {
+        println!("Took {}", samples_taken(&buffer));
+    }
+
+    println!("Saving to: {output}");
+    save_image(&image, &output)?;
+    if let Some(heatmap) = &args.heatmap {
+        println!("Saving sample counts to: {heatmap}");
+        save_image(&buffer.heatmap(), heatmap)?;
+    }
 
     Ok(())
 }
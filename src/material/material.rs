@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use crate::core::{Color, HitRecord, Ray, Vec3};
+use crate::material::scatter::{Scatter, ScatterResult};
 use crate::pixels::texture::Texture;
 use crate::random_float;
 
@@ -12,18 +15,21 @@ pub struct Material {
     pub emission: Option<Color>,  // Light source
 }
 
-#[derive(Debug, Clone)]
-pub struct ScatterResult {
-    pub scattered_ray: Ray,
-    pub attenuation: Color,
+/// The ways a classic material can scatter a ray, picked at random by weight.
+enum Lobe {
+    Diffuse,
+    Reflect,
+    Refract,
 }
 
-impl Material {
-    pub fn value_at(&self, u: f32, v: f32) -> Color {
-        self.texture.value_at(u, v)
+impl From<Material> for Arc<dyn Scatter> {
+    fn from(material: Material) -> Self {
+        Arc::new(material)
     }
+}
 
-    pub fn scatter(&self, ray_in: &Ray, hit: &HitRecord) -> Option<ScatterResult> {
+impl Scatter for Material {
+    fn sample(&self, ray_in: &Ray, hit: &HitRecord) -> Option<ScatterResult> {
         let diffuse = self.diffuse.clamp(0.0, 1.0);
         let reflectivity = self.reflectivity.clamp(0.0, 1.0);
         let transparency = self.transparency.clamp(0.0, 1.0);
@@ -33,46 +39,92 @@ impl Material {
             return None;
         }
 
-        match select_scatter(diffuse, reflectivity, transparency) {
-            Some("diffuse") => {
-                let scatter = self.scatter_diffuse(hit);
-                Some(ScatterResult {
-                    scattered_ray: scatter.scattered_ray,
-                    attenuation: scatter.attenuation * (diffuse / total) * diffuse,
-                })
-            }
-            Some("reflect") => self
+        match select_lobe(diffuse, reflectivity, transparency)? {
+            Lobe::Diffuse => Some(self.scatter_diffuse(hit)),
+            Lobe::Reflect => self
                 .scatter_reflection(ray_in, hit)
                 .map(|scatter| ScatterResult {
-                    scattered_ray: scatter.scattered_ray,
                     attenuation: scatter.attenuation * (reflectivity / total) * reflectivity,
+                    ..scatter
                 }),
-            Some("refract") => {
+            Lobe::Refract => {
                 let scatter = self.scatter_refraction(ray_in, hit);
                 Some(ScatterResult {
-                    scattered_ray: scatter.scattered_ray,
                     attenuation: scatter.attenuation * (transparency / total),
+                    ..scatter
                 })
             }
-            _ => None,
+        }
+    }
+
+    /// Only the diffuse lobe, with its share of the light: the reflection and
+    /// refraction lobes are specular.
+    fn evaluate(&self, _ray_in: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        let cos_theta = direction.dot(hit.normal);
+        if cos_theta <= 0.0 {
+            return Color::BLACK;
+        }
+        self.diffuse_attenuation(hit) * (self.diffuse_chance() * cos_theta / std::f32::consts::PI)
+    }
+
+    /// The diffuse lobe is picked with its share of the weights, then
+    /// samples the hemisphere cosine-weighted.
+    fn pdf(&self, _ray_in: &Ray, hit: &HitRecord, direction: Vec3) -> f32 {
+        let cos_theta = direction.dot(hit.normal);
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+        self.diffuse_chance() * cos_theta / std::f32::consts::PI
+    }
+
+    fn value_at(&self, u: f32, v: f32) -> Color {
+        self.texture.value_at(u, v)
+    }
+
+    fn emission(&self) -> Option<Color> {
+        self.emission
+    }
+}
+
+impl Material {
+    /// How often `sample` picks the diffuse lobe.
+    fn diffuse_chance(&self) -> f32 {
+        let diffuse = self.diffuse.clamp(0.0, 1.0);
+        let total = diffuse + self.reflectivity.clamp(0.0, 1.0) + self.transparency.clamp(0.0, 1.0);
+        if total > 0.0 {
+            diffuse / total
+        } else {
+            0.0
         }
     }
 
     fn scatter_diffuse(&self, hit: &HitRecord) -> ScatterResult {
-        let scatter_dir = Vec3::random_in_hemisphere(hit.normal);
+        // Cosine-weighted, so the cosine and the 1/π of the Lambertian BRDF
+        // cancel against the pdf
+        let scatter_dir = Vec3::random_cosine_direction(hit.normal);
 
         // Apply epsilon offset along the normal to prevent self-intersection
         let epsilon = 1e-4;
         let point = hit.p + hit.normal * epsilon;
         let ray = Ray::new(point, scatter_dir);
-        let surface_color = self.texture.value_at(hit.u, hit.v);
 
         ScatterResult {
             scattered_ray: ray,
-            attenuation: surface_color * (1.0 / std::f32::consts::PI),
+            attenuation: self.diffuse_attenuation(hit),
+            is_specular: false,
         }
     }
 
+    /// Attenuation of a diffuse bounce: the surface color, scaled down when the
+    /// lobe weights add up to less than 1. The diffuse lobe's share of the light
+    /// is already accounted for by how often it is picked.
+    fn diffuse_attenuation(&self, hit: &HitRecord) -> Color {
+        let total = self.diffuse.clamp(0.0, 1.0)
+            + self.reflectivity.clamp(0.0, 1.0)
+            + self.transparency.clamp(0.0, 1.0);
+        self.texture.value_at(hit.u, hit.v) * total.min(1.0)
+    }
+
     fn scatter_reflection(&self, ray_in: &Ray, hit: &HitRecord) -> Option<ScatterResult> {
         let reflected = ray_in.direction().reflect(hit.normal).normalize();
         let fuzz = self.diffuse;
@@ -88,6 +140,7 @@ impl Material {
             Some(ScatterResult {
                 scattered_ray: ray,
                 attenuation: surface_color,
+                is_specular: true,
             })
         } else {
             None
@@ -123,11 +176,12 @@ impl Material {
         ScatterResult {
             scattered_ray: ray,
             attenuation,
+            is_specular: true,
         }
     }
 }
 
-pub fn select_scatter(diffuse: f32, reflectivity: f32, transparency: f32) -> Option<&'static str> {
+fn select_lobe(diffuse: f32, reflectivity: f32, transparency: f32) -> Option<Lobe> {
     let diffuse = diffuse.clamp(0.0, 1.0);
     let reflectivity = reflectivity.clamp(0.0, 1.0);
     let transparency = transparency.clamp(0.0, 1.0);
@@ -140,11 +194,11 @@ pub fn select_scatter(diffuse: f32, reflectivity: f32, transparency: f32) -> Opt
     let choice = random_float() * total;
 
     if choice < diffuse {
-        Some("diffuse")
+        Some(Lobe::Diffuse)
     } else if choice < diffuse + reflectivity {
-        Some("reflect")
+        Some(Lobe::Reflect)
     } else {
-        Some("refract")
+        Some(Lobe::Refract)
     }
 }
 
@@ -152,3 +206,163 @@ fn schlick(cosine: f32, refraction_index: f32) -> f32 {
     let r0 = ((1.0 - refraction_index) / (1.0 + refraction_index)).powi(2);
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Point3;
+    use crate::seed_rng;
+    use std::f32::consts::PI;
+
+    const SAMPLES: usize = 200_000;
+
+    fn material(color: Color, diffuse: f32, reflectivity: f32, transparency: f32) -> Material {
+        Material {
+            texture: Texture::SolidColor(color),
+            diffuse,
+            reflectivity,
+            transparency,
+            index_of_refraction: 1.5,
+            emission: None,
+        }
+    }
+
+    fn hit(material: &Material) -> HitRecord<'_> {
+        HitRecord {
+            p: Point3::ZERO,
+            normal: Vec3::Y,
+            t: 1.0,
+            u: 0.5,
+            v: 0.5,
+            front_face: true,
+            material,
+        }
+    }
+
+    fn incoming() -> Ray {
+        let from = Point3::new(1.0, 1.0, 0.0);
+        Ray::new(from, -from)
+    }
+
+    #[test]
+    fn diffuse_pdf_is_cosine_weighted() {
+        seed_rng(21);
+        let white = material(Color::WHITE, 1.0, 0.0, 0.0);
+        let hit = hit(&white);
+
+        assert_eq!(white.pdf(&incoming(), &hit, Vec3::Y), 1.0 / PI);
+        assert_eq!(white.pdf(&incoming(), &hit, -Vec3::Y), 0.0);
+        assert_eq!(white.pdf(&incoming(), &hit, Vec3::X), 0.0);
+
+        // Integrates to one over the hemisphere
+        let mut integral = 0.0f64;
+        for _ in 0..SAMPLES {
+            let direction = Vec3::random_in_hemisphere(hit.normal);
+            integral += (white.pdf(&incoming(), &hit, direction) * 2.0 * PI) as f64;
+        }
+        let integral = integral / SAMPLES as f64;
+        assert!((integral - 1.0).abs() < 0.01, "integral {integral}");
+
+        // Diffuse bounces follow it: the mean cosine of cos θ / π is 2/3
+        let mut cosine = 0.0f64;
+        for _ in 0..SAMPLES {
+            let scatter = white.sample(&incoming(), &hit).unwrap();
+            let direction = scatter.scattered_ray.direction();
+            assert!((direction.length() - 1.0).abs() < 1e-4);
+            assert!(direction.dot(hit.normal) >= 0.0);
+            cosine += direction.dot(hit.normal) as f64;
+        }
+        let cosine = cosine / SAMPLES as f64;
+        assert!((cosine - 2.0 / 3.0).abs() < 0.005, "mean cosine {cosine}");
+    }
+
+    /// For a direction a diffuse bounce picks, `evaluate / pdf` has to be the
+    /// attenuation it gets.
+    #[test]
+    fn evaluate_over_pdf_is_the_diffuse_attenuation() {
+        seed_rng(22);
+        let materials = [
+            material(Color::WHITE, 1.0, 0.0, 0.0),
+            material(Color::new(0.8, 0.4, 0.1), 0.6, 0.0, 0.0),
+            material(Color::new(0.2, 0.6, 0.9), 0.5, 0.5, 0.0),
+            material(Color::WHITE, 0.3, 0.0, 0.3),
+        ];
+        for material in materials {
+            let hit = hit(&material);
+            let total = material.diffuse + material.reflectivity + material.transparency;
+            let mut diffuse_bounces = 0;
+            for _ in 0..SAMPLES / 10 {
+                let Some(scatter) = material.sample(&incoming(), &hit) else {
+                    continue;
+                };
+                if scatter.is_specular {
+                    continue;
+                }
+                diffuse_bounces += 1;
+                let direction = scatter.scattered_ray.direction();
+                let weight = material.evaluate(&incoming(), &hit, direction)
+                    * (1.0 / material.pdf(&incoming(), &hit, direction));
+                for (weight, attenuation) in [
+                    (weight.r, scatter.attenuation.r),
+                    (weight.g, scatter.attenuation.g),
+                    (weight.b, scatter.attenuation.b),
+                ] {
+                    assert!((weight - attenuation).abs() < 1e-4, "{material:?}");
+                }
+            }
+
+            // Diffuse bounces are picked by the lobe's share of the weights,
+            // which is what the pdf integrates to
+            let share = diffuse_bounces as f32 / (SAMPLES / 10) as f32;
+            let expected = material.diffuse / total;
+            assert!((share - expected).abs() < 0.01, "{material:?}: {share}");
+
+            let mut integral = 0.0f64;
+            for _ in 0..SAMPLES / 10 {
+                let direction = Vec3::random_in_hemisphere(hit.normal);
+                integral += (material.pdf(&incoming(), &hit, direction) * 2.0 * PI) as f64;
+            }
+            let integral = (integral / (SAMPLES / 10) as f64) as f32;
+            assert!(
+                (integral - expected).abs() < 0.01,
+                "{material:?}: {integral}"
+            );
+        }
+    }
+
+    /// A Lambertian lobe of weight `diffuse` reflects `color * diffuse / π`
+    /// per unit solid angle, times the cosine.
+    #[test]
+    fn evaluate_is_the_diffuse_bsdf_times_cosine() {
+        let color = Color::new(0.8, 0.4, 0.1);
+        let direction = Vec3::new(0.0, 0.5, 0.5f32.sqrt()).normalize();
+        let cos_theta = direction.dot(Vec3::Y);
+        for (diffuse, reflectivity, transparency) in
+            [(1.0, 0.0, 0.0), (0.6, 0.0, 0.0), (0.3, 0.5, 0.2)]
+        {
+            let material = material(color, diffuse, reflectivity, transparency);
+            let hit = hit(&material);
+            let value = material.evaluate(&incoming(), &hit, direction);
+            let expected = color * (diffuse * cos_theta / PI);
+            for (value, expected) in [
+                (value.r, expected.r),
+                (value.g, expected.g),
+                (value.b, expected.b),
+            ] {
+                assert!(
+                    (value - expected).abs() < 1e-6,
+                    "{material:?}: {value} {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_is_evaluated_below_the_surface() {
+        let white = material(Color::WHITE, 1.0, 0.0, 0.0);
+        let hit = hit(&white);
+        let below = Vec3::new(0.3, -0.5, 0.1).normalize();
+        assert_eq!(white.evaluate(&incoming(), &hit, below), Color::BLACK);
+        assert_eq!(white.pdf(&incoming(), &hit, below), 0.0);
+    }
+}
@@ -1,3 +1,8 @@
+#[allow(clippy::module_inception)]
 pub mod material;
+pub mod pbr;
+pub mod scatter;
 
 pub use material::Material;
+pub use pbr::PbrMaterial;
+pub use scatter::{Scatter, ScatterResult};
@@ -0,0 +1,383 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::core::{Color, HitRecord, Ray, Vec3};
+use crate::material::scatter::{Scatter, ScatterResult};
+use crate::pixels::texture::Texture;
+use crate::random_float;
+
+/// Below this roughness the specular lobe is too sharp to find by sampling
+/// lights, so it is left to reflected rays, like a mirror.
+const SHARP_ROUGHNESS: f32 = 0.1;
+
+/// Metallic-roughness material: a GGX microfacet specular lobe over a
+/// Lambertian base, as used by most PBR authoring tools.
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    pub base_color: Texture,     // Diffuse color, or reflectance for metals
+    pub metallic: f32,           // 0 = dielectric, 1 = metal
+    pub roughness: f32,          // 0 = polished, 1 = fully rough
+    pub specular: f32,           // Dielectric reflectance; 0.5 = 4% at normal incidence
+    pub emission: Option<Color>, // Light source
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Texture::SolidColor(Color::WHITE),
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            emission: None,
+        }
+    }
+}
+
+/// Everything about the hit that does not depend on the incoming direction.
+struct Lobes {
+    normal: Vec3,
+    outgoing: Vec3, // towards the viewer
+    cos_out: f32,
+    diffuse: Color, // Lambertian albedo, already reduced by the light the coat reflects
+    f0: Color,      // Specular reflectance at normal incidence
+    alpha: f32,     // GGX width
+    spec_chance: f32, // Probability of sampling the specular lobe
+}
+
+impl From<PbrMaterial> for Arc<dyn Scatter> {
+    fn from(material: PbrMaterial) -> Self {
+        Arc::new(material)
+    }
+}
+
+impl Scatter for PbrMaterial {
+    fn sample(&self, ray_in: &Ray, hit: &HitRecord) -> Option<ScatterResult> {
+        let lobes = self.lobes(ray_in, hit);
+        let (tangent, bitangent) = lobes.normal.tangent_frame();
+        let to_world = |x: f32, y: f32, z: f32| tangent * x + bitangent * y + lobes.normal * z;
+
+        // Each lobe is weighted only by itself, so a sharp specular lobe can be
+        // left out of light sampling without counting anything twice
+        let (direction, attenuation, is_specular) = if random_float() < lobes.spec_chance {
+            // Sample a microfacet normal from the GGX distribution and mirror around it
+            let u1 = random_float();
+            let phi = 2.0 * PI * random_float();
+            let tan2 = lobes.alpha * lobes.alpha * u1 / (1.0 - u1).max(1e-6);
+            let cos_h = 1.0 / (1.0 + tan2).sqrt();
+            let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+            let half = to_world(sin_h * phi.cos(), sin_h * phi.sin(), cos_h);
+
+            let direction = (-lobes.outgoing).reflect(half);
+            let cos_in = lobes.normal.dot(direction);
+            if cos_in <= 0.0 {
+                return None;
+            }
+            // D cancels against the sampling density, leaving F G (o·h) / (cos_out cos_h);
+            // evaluating it directly keeps near-mirror surfaces free of rounding noise
+            let out_dot_half = lobes.outgoing.dot(half).max(0.0);
+            let g = smith_g1(lobes.cos_out, lobes.alpha) * smith_g1(cos_in, lobes.alpha);
+            let weight = schlick(lobes.f0, out_dot_half)
+                * (g * out_dot_half / (lobes.cos_out * cos_h * lobes.spec_chance));
+            (direction, weight, self.is_sharp())
+        } else {
+            // Cosine-weighted hemisphere: the cosine and 1/π cancel against the pdf
+            (
+                Vec3::random_cosine_direction(lobes.normal),
+                lobes.diffuse * (1.0 / (1.0 - lobes.spec_chance)),
+                false,
+            )
+        };
+
+        // Apply epsilon offset along the normal to prevent self-intersection
+        let point = hit.p + hit.normal * 1e-4;
+        Some(ScatterResult {
+            scattered_ray: Ray::new(point, direction),
+            attenuation,
+            is_specular,
+        })
+    }
+
+    /// A sharp specular lobe is left out: `sample` marks its bounces as specular.
+    fn evaluate(&self, ray_in: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        let lobes = self.lobes(ray_in, hit);
+        let cos_in = lobes.normal.dot(direction);
+        if cos_in <= 0.0 {
+            return Color::BLACK;
+        }
+
+        let diffuse = lobes.diffuse * (cos_in / PI);
+        if self.is_sharp() {
+            return diffuse;
+        }
+        diffuse + specular_term(&lobes, direction)
+    }
+
+    /// Both lobes, weighted by how often each is picked, or only the diffuse
+    /// one when the specular lobe is sharp, like in `evaluate`.
+    fn pdf(&self, ray_in: &Ray, hit: &HitRecord, direction: Vec3) -> f32 {
+        let lobes = self.lobes(ray_in, hit);
+        let cos_in = lobes.normal.dot(direction);
+        if cos_in <= 0.0 {
+            return 0.0;
+        }
+
+        let diffuse_pdf = (1.0 - lobes.spec_chance) * cos_in / PI;
+        if self.is_sharp() {
+            return diffuse_pdf;
+        }
+        let half = (lobes.outgoing + direction).normalize();
+        let cos_h = lobes.normal.dot(half).max(0.0);
+        let specular_pdf =
+            ggx_d(cos_h, lobes.alpha) * cos_h / (4.0 * lobes.outgoing.dot(half).abs().max(1e-4));
+        lobes.spec_chance * specular_pdf + diffuse_pdf
+    }
+
+    fn value_at(&self, u: f32, v: f32) -> Color {
+        self.base_color.value_at(u, v)
+    }
+
+    fn emission(&self) -> Option<Color> {
+        self.emission
+    }
+}
+
+impl PbrMaterial {
+    fn lobes(&self, ray_in: &Ray, hit: &HitRecord) -> Lobes {
+        let base = self.base_color.value_at(hit.u, hit.v);
+        let metallic = self.metallic.clamp(0.0, 1.0);
+        let roughness = self.roughness.clamp(0.0, 1.0);
+
+        let outgoing = -ray_in.direction().normalize();
+        let cos_out = hit.normal.dot(outgoing).max(1e-4);
+
+        let dielectric_f0 = Color::WHITE * (0.08 * self.specular.clamp(0.0, 1.0));
+        let f0 = Color::lerp(dielectric_f0, base, metallic);
+        let coat = schlick(f0, cos_out);
+        let diffuse = base * one_minus(coat) * (1.0 - metallic);
+
+        let spec_weight = coat.luminance();
+        let total = spec_weight + diffuse.luminance();
+        let spec_chance = if total > 0.0 {
+            spec_weight / total
+        } else {
+            0.5
+        };
+
+        Lobes {
+            normal: hit.normal,
+            outgoing,
+            cos_out,
+            diffuse,
+            f0,
+            alpha: (roughness * roughness).max(1e-3),
+            spec_chance,
+        }
+    }
+
+    fn is_sharp(&self) -> bool {
+        self.roughness < SHARP_ROUGHNESS
+    }
+}
+
+/// Cook-Torrance specular BSDF times the cosine of `incoming`.
+fn specular_term(lobes: &Lobes, incoming: Vec3) -> Color {
+    let half = (lobes.outgoing + incoming).normalize();
+    let cos_in = lobes.normal.dot(incoming).max(1e-4);
+    let cos_h = lobes.normal.dot(half).max(0.0);
+
+    let d = ggx_d(cos_h, lobes.alpha);
+    let g = smith_g1(lobes.cos_out, lobes.alpha) * smith_g1(cos_in, lobes.alpha);
+    let f = schlick(lobes.f0, lobes.outgoing.dot(half).max(0.0));
+
+    // D G F / (4 cos_out cos_in), times cos_in
+    f * (d * g / (4.0 * lobes.cos_out))
+}
+
+fn ggx_d(cos_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let denom = cos_h * cos_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * denom * denom)
+}
+
+fn smith_g1(cos: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    2.0 * cos / (cos + (a2 + (1.0 - a2) * cos * cos).sqrt())
+}
+
+fn schlick(f0: Color, cosine: f32) -> Color {
+    let t = (1.0 - cosine).clamp(0.0, 1.0).powi(5);
+    f0 + one_minus(f0) * t
+}
+
+fn one_minus(c: Color) -> Color {
+    Color::new(1.0 - c.r, 1.0 - c.g, 1.0 - c.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Point3;
+    use crate::seed_rng;
+
+    const SAMPLES: usize = 400_000;
+
+    fn hit(material: &PbrMaterial) -> HitRecord<'_> {
+        HitRecord {
+            p: Point3::ZERO,
+            normal: Vec3::Z,
+            t: 1.0,
+            u: 0.5,
+            v: 0.5,
+            front_face: true,
+            material,
+        }
+    }
+
+    /// Rays arriving at 0°, 45° and 75° from the normal.
+    fn incoming_rays() -> Vec<Ray> {
+        [0.0f32, 45.0, 75.0]
+            .iter()
+            .map(|angle| {
+                let angle = angle.to_radians();
+                let from = Point3::new(angle.sin(), 0.0, angle.cos());
+                Ray::new(from, -from)
+            })
+            .collect()
+    }
+
+    fn materials() -> Vec<PbrMaterial> {
+        vec![
+            PbrMaterial {
+                base_color: Texture::SolidColor(Color::new(0.8, 0.5, 0.2)),
+                roughness: 0.5,
+                ..PbrMaterial::default()
+            },
+            PbrMaterial {
+                base_color: Texture::SolidColor(Color::new(0.9, 0.7, 0.3)),
+                metallic: 1.0,
+                roughness: 0.3,
+                ..PbrMaterial::default()
+            },
+            PbrMaterial {
+                roughness: 0.9,
+                specular: 1.0,
+                ..PbrMaterial::default()
+            },
+        ]
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32, what: &str) {
+        assert!(
+            (actual - expected).abs() <= tolerance * expected.abs().max(1.0),
+            "{what}: got {actual}, expected {expected}"
+        );
+    }
+
+    /// Averages over the directions `sample` picks must match the same
+    /// averages weighted by `pdf`. The x component tells whether the specular
+    /// lobe leans the right way, the z component whether it has the right width.
+    #[test]
+    fn pdf_is_the_density_of_sample() {
+        seed_rng(11);
+        for material in materials() {
+            let hit = hit(&material);
+            for ray in incoming_rays() {
+                let mut sampled = Vec3::ZERO;
+                for _ in 0..SAMPLES {
+                    if let Some(scatter) = material.sample(&ray, &hit) {
+                        sampled += scatter.scattered_ray.direction().normalize();
+                    }
+                }
+
+                let mut integrated = Vec3::ZERO;
+                for _ in 0..SAMPLES {
+                    let direction = Vec3::random_in_hemisphere(hit.normal);
+                    integrated += direction * (material.pdf(&ray, &hit, direction) * 2.0 * PI);
+                }
+
+                let what = format!("{material:?} {ray:?}");
+                let sampled = sampled / SAMPLES as f32;
+                let integrated = integrated / SAMPLES as f32;
+                assert_close(integrated.x, sampled.x, 0.03, &what);
+                assert_close(integrated.z, sampled.z, 0.03, &what);
+            }
+        }
+    }
+
+    /// Bounces that are not specular are exactly those the density covers. A
+    /// sharp specular lobe is left out of it.
+    #[test]
+    fn pdf_integrates_to_the_chance_of_a_bounce_that_is_not_specular() {
+        seed_rng(12);
+        let sharp = PbrMaterial {
+            base_color: Texture::SolidColor(Color::new(0.2, 0.4, 0.8)),
+            roughness: 0.05,
+            specular: 1.0,
+            ..PbrMaterial::default()
+        };
+        for material in materials().into_iter().chain([sharp]) {
+            let hit = hit(&material);
+            for ray in incoming_rays() {
+                let mut scattered = 0;
+                for _ in 0..SAMPLES {
+                    if let Some(scatter) = material.sample(&ray, &hit) {
+                        scattered += !scatter.is_specular as usize;
+                    }
+                }
+
+                let mut integral = 0.0f64;
+                for _ in 0..SAMPLES {
+                    let direction = Vec3::random_in_hemisphere(hit.normal);
+                    integral += (material.pdf(&ray, &hit, direction) * 2.0 * PI) as f64;
+                }
+
+                let chance = scattered as f32 / SAMPLES as f32;
+                let integral = (integral / SAMPLES as f64) as f32;
+                assert!(chance > 0.25, "{material:?} {ray:?}: scatters {chance}");
+                assert_close(integral, chance, 0.03, &format!("{material:?} {ray:?}"));
+            }
+        }
+    }
+
+    /// The BSDF times cosine divided by the density averages out to the
+    /// attenuation `sample` gives.
+    #[test]
+    fn evaluate_over_pdf_matches_sample() {
+        seed_rng(13);
+        for material in materials() {
+            let hit = hit(&material);
+            for ray in incoming_rays() {
+                let mut sampled = [0.0f64; 3];
+                let mut evaluated = [0.0f64; 3];
+                for _ in 0..SAMPLES {
+                    let Some(scatter) = material.sample(&ray, &hit) else {
+                        continue;
+                    };
+                    assert!(!scatter.is_specular);
+                    let direction = scatter.scattered_ray.direction();
+                    let weight = material.evaluate(&ray, &hit, direction)
+                        * (1.0 / material.pdf(&ray, &hit, direction));
+                    let attenuation = scatter.attenuation;
+                    for (sum, value) in
+                        sampled
+                            .iter_mut()
+                            .zip([attenuation.r, attenuation.g, attenuation.b])
+                    {
+                        *sum += value as f64;
+                    }
+                    for (sum, value) in evaluated.iter_mut().zip([weight.r, weight.g, weight.b]) {
+                        *sum += value as f64;
+                    }
+                }
+                for (sampled, evaluated) in sampled.iter().zip(evaluated) {
+                    assert_close(
+                        (evaluated / SAMPLES as f64) as f32,
+                        (sampled / SAMPLES as f64) as f32,
+                        0.02,
+                        &format!("{material:?} {ray:?}"),
+                    );
+                }
+            }
+        }
+    }
+}
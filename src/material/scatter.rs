@@ -0,0 +1,44 @@
+use std::fmt::Debug;
+
+use crate::core::{Color, HitRecord, Ray, Vec3};
+
+#[derive(Debug, Clone)]
+pub struct ScatterResult {
+    pub scattered_ray: Ray,
+    pub attenuation: Color,
+    /// Reflection or refraction, which light sampling and `evaluate` leave out
+    pub is_specular: bool,
+}
+
+/// How a surface reflects, transmits and emits light.
+///
+/// Objects hold materials as `Arc<dyn Scatter>`, so any type implementing this
+/// trait can be put on them, including ones defined outside this crate.
+///
+/// Bounces that `sample` marks as specular are left to find lights on their
+/// own, and lights are sampled directly for the rest of the material, so
+/// `evaluate` and `pdf` cover only the lobes that are not specular. For a
+/// direction picked by such a bounce, `evaluate` divided by `pdf` equals the
+/// attenuation `sample` returns on average.
+pub trait Scatter: Debug + Send + Sync {
+    /// Pick the direction the path continues in, with the color it is filtered by.
+    /// `None` absorbs the path.
+    fn sample(&self, ray_in: &Ray, hit: &HitRecord) -> Option<ScatterResult>;
+
+    /// BSDF times cosine of the lobes that are not specular, for light arriving
+    /// from `direction` and leaving towards the viewer along `ray_in`. Black if
+    /// no such lobe reaches `direction`.
+    fn evaluate(&self, ray_in: &Ray, hit: &HitRecord, direction: Vec3) -> Color;
+
+    /// Density with which `sample` returns `direction` from a bounce that is
+    /// not specular, per unit solid angle. Zero where `evaluate` is black.
+    fn pdf(&self, ray_in: &Ray, hit: &HitRecord, direction: Vec3) -> f32;
+
+    /// Surface color at the given texture coordinates.
+    fn value_at(&self, u: f32, v: f32) -> Color;
+
+    /// Light given off by the surface, if it is a light source.
+    fn emission(&self) -> Option<Color> {
+        None
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use crate::core::{Aabb, HitRecord, Hittable, Ray};
+
+/// Bounding volume hierarchy over a set of hittables.
+///
+/// Each node holds two children and the box enclosing both, so a ray only
+/// descends into subtrees whose boxes it actually crosses.
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    /// Build a hierarchy by recursively splitting the objects in half along
+    /// the longest axis of their centroids' bounds.
+    ///
+    /// Panics if `objects` is empty.
+    pub fn new(mut objects: Vec<Arc<dyn Hittable>>) -> Self {
+        assert!(!objects.is_empty(), "cannot build a BVH over zero objects");
+
+        let centroid_bounds = objects
+            .iter()
+            .map(|o| {
+                let c = o.bounding_box().centroid();
+                Aabb::new(c, c)
+            })
+            .reduce(Aabb::surrounding)
+            .unwrap();
+        let axis = centroid_bounds.longest_axis();
+
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            1 => (objects[0].clone(), objects[0].clone()),
+            2 => (objects[0].clone(), objects[1].clone()),
+            _ => {
+                objects.sort_by(|a, b| {
+                    let ca = a.bounding_box().centroid()[axis];
+                    let cb = b.bounding_box().centroid()[axis];
+                    ca.total_cmp(&cb)
+                });
+                let rest = objects.split_off(objects.len() / 2);
+                (Arc::new(BvhNode::new(objects)), Arc::new(BvhNode::new(rest)))
+            }
+        };
+
+        let bbox = Aabb::surrounding(left.bounding_box(), right.bounding_box());
+        Self { left, right, bbox }
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        let left_hit = self.left.hit(ray, t_min, t_max);
+        let closest = left_hit.as_ref().map_or(t_max, |hit| hit.t);
+        let right_hit = self.right.hit(ray, t_min, closest);
+
+        right_hit.or(left_hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Color, Point3, Vec3};
+    use crate::material::Material;
+    use crate::objects::{Cube, Sphere};
+    use crate::pixels::texture::Texture;
+    use crate::{random_range, seed_rng};
+
+    fn material() -> Material {
+        Material {
+            texture: Texture::SolidColor(Color::WHITE),
+            diffuse: 1.0,
+            reflectivity: 0.0,
+            transparency: 0.0,
+            index_of_refraction: 1.0,
+            emission: None,
+        }
+    }
+
+    fn random_point(range: f32) -> Point3 {
+        Point3::new(
+            random_range(-range..range),
+            random_range(-range..range),
+            random_range(-range..range),
+        )
+    }
+
+    fn linear_hit<'a>(
+        objects: &'a [Arc<dyn Hittable>],
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let mut closest = t_max;
+        let mut result = None;
+        for object in objects {
+            if let Some(hit) = object.hit(ray, t_min, closest) {
+                closest = hit.t;
+                result = Some(hit);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn finds_the_same_hits_as_a_linear_scan() {
+        seed_rng(1);
+        let objects: Vec<Arc<dyn Hittable>> = (0..200)
+            .map(|i| -> Arc<dyn Hittable> {
+                let center = random_point(20.0);
+                let size = random_range(0.1..1.5);
+                if i % 2 == 0 {
+                    Arc::new(Sphere::new(center, size, material()))
+                } else {
+                    Arc::new(Cube::new(center, size, material()))
+                }
+            })
+            .collect();
+        let bvh = BvhNode::new(objects.clone());
+
+        let mut hits = 0;
+        for i in 0..5000 {
+            let origin = random_point(25.0);
+            // Every tenth ray runs along an axis, where the slab test divides by zero
+            let direction = if i % 10 == 0 {
+                [Vec3::X, -Vec3::Y, Vec3::Z][i / 10 % 3]
+            } else {
+                random_point(15.0) - origin
+            };
+            let ray = Ray::new(origin, direction);
+            let t_max = if i % 3 == 0 { 10.0 } else { f32::INFINITY };
+
+            let expected = linear_hit(&objects, &ray, 1e-4, t_max);
+            let actual = bvh.hit(&ray, 1e-4, t_max);
+            match (expected, actual) {
+                (None, None) => {}
+                (Some(expected), Some(actual)) => {
+                    hits += 1;
+                    assert_eq!(expected.t, actual.t, "ray {i}");
+                    assert_eq!(expected.p, actual.p, "ray {i}");
+                    assert_eq!(expected.normal, actual.normal, "ray {i}");
+                }
+                (expected, actual) => panic!(
+                    "ray {i}: linear scan hit at {:?}, BVH at {:?}",
+                    expected.map(|hit| hit.t),
+                    actual.map(|hit| hit.t)
+                ),
+            }
+        }
+        assert!(hits > 1000, "only {hits} rays hit anything");
+    }
+
+    #[test]
+    fn box_encloses_every_object() {
+        seed_rng(2);
+        let objects: Vec<Arc<dyn Hittable>> = (0..50)
+            .map(|_| -> Arc<dyn Hittable> {
+                Arc::new(Sphere::new(
+                    random_point(10.0),
+                    random_range(0.1..2.0),
+                    material(),
+                ))
+            })
+            .collect();
+        let bbox = BvhNode::new(objects.clone()).bounding_box();
+        for object in &objects {
+            let inner = object.bounding_box();
+            assert_eq!(Aabb::surrounding(bbox, inner), bbox);
+        }
+    }
+}
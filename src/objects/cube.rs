@@ -1,20 +1,23 @@
-use crate::core::{HitRecord, Hittable, Point3, Ray, Vec3};
-use crate::material::Material;
+use std::sync::Arc;
+
+use crate::core::{Aabb, Color, HitRecord, Hittable, LightSample, Point3, Ray, Vec3};
+use crate::material::Scatter;
+use crate::random_float;
 
 #[derive(Clone)]
 pub struct Cube {
     pub min: Point3,
     pub max: Point3,
-    material: Material,
+    material: Arc<dyn Scatter>,
 }
 
 // A cube is defined by its minimum and maximum corner points
 impl Cube {
-    pub fn new(center: Point3, size: f32, material: Material) -> Self {
+    pub fn new(center: Point3, size: f32, material: impl Into<Arc<dyn Scatter>>) -> Self {
         let half = size / 2.0;
         let min = center - Vec3::new(half, half, half);
         let max = center + Vec3::new(half, half, half);
-        Self { min, max, material}//
+        Self { min, max, material: material.into() }//
     }
 
     fn compute_normal(&self, point: Point3) -> Vec3 {
@@ -62,13 +65,13 @@ impl Cube {
         (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))
     }
 
-    pub fn set_material(&mut self, material: Material) {
-        self.material = material;
+    pub fn set_material(&mut self, material: impl Into<Arc<dyn Scatter>>) {
+        self.material = material.into();
     }
 }
 
 impl Hittable for Cube {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let mut t_enter = t_min;
         let mut t_exit = t_max;
 
@@ -100,17 +103,63 @@ impl Hittable for Cube {
         let outward_normal = self.compute_normal(point);
         let (normal, front_face) = HitRecord::face_normal(ray, outward_normal);
         let (u, v) = self.compute_uv(point);
-        let color = self.material.value_at(u, v);
 
         Some(HitRecord {
             p: point,   // hit_point
             normal,     // surface normal
             t,          // distance along ray
-            color,      // surface color
             u,          // texture coordinate u
             v,          // texture coordinate v
             front_face, // whether the ray hits the front face
-            material: self.material.clone(),
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.min, self.max)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.emission().is_some()
+    }
+
+    fn sample_surface(&self, _origin: Point3) -> Option<LightSample> {
+        let extent = self.max - self.min;
+        // Area of one face perpendicular to each axis
+        let face_areas = [
+            extent.y * extent.z,
+            extent.x * extent.z,
+            extent.x * extent.y,
+        ];
+        let total_area = 2.0 * (face_areas[0] + face_areas[1] + face_areas[2]);
+
+        // Pick a face with probability proportional to its area
+        let mut choice = random_float() * total_area;
+        let mut axis = 2;
+        for (i, area) in face_areas.iter().enumerate() {
+            if choice < 2.0 * area {
+                axis = i;
+                break;
+            }
+            choice -= 2.0 * area;
+        }
+        let positive_side = random_float() < 0.5;
+
+        let mut p = self.min + Vec3::new(random_float(), random_float(), random_float()) * extent;
+        let mut normal = Vec3::ZERO;
+        if positive_side {
+            p[axis] = self.max[axis];
+            normal[axis] = 1.0;
+        } else {
+            p[axis] = self.min[axis];
+            normal[axis] = -1.0;
+        }
+
+        Some(LightSample {
+            p,
+            normal,
+            pdf: 1.0 / total_area,
+            emission: self.material.emission().unwrap_or(Color::BLACK),
         })
     }
 }
@@ -1,17 +1,21 @@
-use crate::core::{HitRecord, Hittable, Point3, Ray, Vec3};
-use crate::material::Material;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::core::{Aabb, Color, HitRecord, Hittable, LightSample, Point3, Ray, Vec3};
+use crate::material::Scatter;
+use crate::random_float;
 
 #[derive(Clone)]
 pub struct Cylinder {
     center: Point3,
     radius: f32,
     height: f32,
-    material: Material,
-    _bounding_box: (Point3, Point3),
+    material: Arc<dyn Scatter>,
+    bounding_box: (Point3, Point3),
 }
 
 impl Cylinder {
-    pub fn new(center: Point3, radius: f32, height: f32, material: Material) -> Self {
+    pub fn new(center: Point3, radius: f32, height: f32, material: impl Into<Arc<dyn Scatter>>) -> Self {
         let min = Point3::new(center.x() - radius, center.y(), center.z() - radius);
 
         let max = Point3::new(
@@ -24,8 +28,8 @@ impl Cylinder {
             center,
             radius,
             height,
-            material,
-            _bounding_box: (min, max),
+            material: material.into(),
+            bounding_box: (min, max),
         }
     }
 
@@ -44,12 +48,12 @@ impl Cylinder {
     }
 
     // Set a new texture for the cylinder
-    pub fn set_material(&mut self, material: Material) {
-        self.material = material;
+    pub fn set_material(&mut self, material: impl Into<Arc<dyn Scatter>>) {
+        self.material = material.into();
     }
 
     // Helper function to check intersection with a cap (top or bottom)
-    fn hit_cap(&self, ray: &Ray, t_min: f32, t_max: f32, y: f32) -> Option<HitRecord> {
+    fn hit_cap(&self, ray: &Ray, t_min: f32, t_max: f32, y: f32) -> Option<HitRecord<'_>> {
         // Skip if ray direction is too small (parallel to plane)
         if ray.direction().y.abs() < 0.001 {
             return None;
@@ -78,21 +82,19 @@ impl Cylinder {
 
         let (normal, front_face) = HitRecord::face_normal(ray, normal);
         let (u, v) = self.compute_uv(point);
-        let color = self.material.value_at(u, v);
 
         Some(HitRecord {
             p: point,
             normal,
             t,
-            color,
             u,
             v,
             front_face,
-            material: self.material.clone(),
+            material: self.material.as_ref(),
         })
     }
 
-    fn hit_side(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit_side(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         // Simple cylinder-ray intersection (infinite cylinder, then clip by height)
         let oc_x = ray.origin().x - self.center.x;
         let oc_z = ray.origin().z - self.center.z;
@@ -121,17 +123,15 @@ impl Cylinder {
                     let outward_normal = self.compute_normal(point);
                     let (normal, front_face) = HitRecord::face_normal(ray, outward_normal);
                     let (u, v) = self.compute_uv(point);
-                    let color = self.material.value_at(u, v);
 
                     return Some(HitRecord {
                         p: point,
                         normal,
                         t,
-                        color,
                         u,
                         v,
                         front_face,
-                        material: self.material.clone(),
+                        material: self.material.as_ref(),
                     });
                 }
             }
@@ -142,8 +142,8 @@ impl Cylinder {
 }
 
 impl Hittable for Cylinder {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let mut closest_hit: Option<HitRecord> = None;
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut closest_hit: Option<HitRecord<'_>> = None;
         let mut closest_t = t_max;
 
         // Check intersection with cylinder side
@@ -165,4 +165,45 @@ impl Hittable for Cylinder {
 
         closest_hit
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let (min, max) = self.bounding_box;
+        Aabb::from_points(min, max)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.emission().is_some()
+    }
+
+    fn sample_surface(&self, _origin: Point3) -> Option<LightSample> {
+        let radius = self.radius.abs();
+        let side_area = 2.0 * PI * radius * self.height;
+        let cap_area = PI * radius * radius;
+        let total_area = side_area + 2.0 * cap_area;
+
+        let phi = 2.0 * PI * random_float();
+        let choice = random_float() * total_area;
+
+        let (p, normal) = if choice < side_area {
+            let normal = Vec3::new(phi.cos(), 0.0, phi.sin());
+            let y = random_float() * self.height;
+            (self.center + normal * radius + Vec3::new(0.0, y, 0.0), normal)
+        } else {
+            // Uniform point on a cap disk
+            let r = radius * random_float().sqrt();
+            let offset = Vec3::new(r * phi.cos(), 0.0, r * phi.sin());
+            if choice < side_area + cap_area {
+                (self.center + offset, -Vec3::Y)
+            } else {
+                (self.center + offset + Vec3::new(0.0, self.height, 0.0), Vec3::Y)
+            }
+        };
+
+        Some(LightSample {
+            p,
+            normal,
+            pdf: 1.0 / total_area,
+            emission: self.material.emission().unwrap_or(Color::BLACK),
+        })
+    }
 }
@@ -0,0 +1,135 @@
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::core::{Aabb, HitRecord, Hittable, LightSample, Point3, Ray};
+use crate::material::Scatter;
+use crate::objects::bvh::BvhNode;
+use crate::objects::obj::ObjModel;
+use crate::objects::triangle::Triangle;
+use crate::random_float;
+
+/// A triangle mesh with its own BVH over the faces.
+pub struct Mesh {
+    bvh: BvhNode,
+    triangles: Vec<Arc<Triangle>>,
+    /// Running sum of triangle areas, for picking triangles proportional to area
+    area_cdf: Vec<f32>,
+    emissive: bool,
+}
+
+impl Mesh {
+    /// Panics if `triangles` is empty.
+    pub fn new(triangles: Vec<Triangle>) -> Self {
+        let triangles: Vec<Arc<Triangle>> = triangles.into_iter().map(Arc::new).collect();
+        let bvh = BvhNode::new(
+            triangles
+                .iter()
+                .map(|t| t.clone() as Arc<dyn Hittable>)
+                .collect(),
+        );
+
+        let area_cdf = triangles
+            .iter()
+            .scan(0.0, |total, t| {
+                *total += t.area();
+                Some(*total)
+            })
+            .collect();
+        let emissive = triangles.iter().any(|t| t.is_emissive());
+
+        Self {
+            bvh,
+            triangles,
+            area_cdf,
+            emissive,
+        }
+    }
+
+    /// Build a mesh from a parsed OBJ model, scaling it uniformly and moving
+    /// its origin to `position`. Vertex normals in the file are used for
+    /// smooth shading; faces without them are shaded flat.
+    pub fn from_obj(
+        model: &ObjModel,
+        position: Point3,
+        scale: f32,
+        material: impl Into<Arc<dyn Scatter>>,
+    ) -> Result<Self> {
+        if model.faces.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "OBJ model has no faces"));
+        }
+        let material: Arc<dyn Scatter> = material.into();
+
+        let triangles = model
+            .faces
+            .iter()
+            .map(|face| {
+                let [a, b, c] =
+                    face.map(|corner| position + model.positions[corner.position] * scale);
+                let mut triangle = Triangle::new(a, b, c, material.clone());
+
+                if let [Some(n0), Some(n1), Some(n2)] = face.map(|corner| corner.normal) {
+                    triangle = triangle.with_normals([
+                        model.normals[n0],
+                        model.normals[n1],
+                        model.normals[n2],
+                    ]);
+                }
+                if let [Some(t0), Some(t1), Some(t2)] = face.map(|corner| corner.texcoord) {
+                    triangle = triangle.with_uvs([
+                        model.texcoords[t0],
+                        model.texcoords[t1],
+                        model.texcoords[t2],
+                    ]);
+                }
+                triangle
+            })
+            .collect();
+
+        Ok(Self::new(triangles))
+    }
+
+    pub fn load_obj(
+        path: impl AsRef<Path>,
+        position: Point3,
+        scale: f32,
+        material: impl Into<Arc<dyn Scatter>>,
+    ) -> Result<Self> {
+        let model = ObjModel::load(path)?;
+        Self::from_obj(&model, position, scale, material)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.emissive
+    }
+
+    fn sample_surface(&self, origin: Point3) -> Option<LightSample> {
+        let total_area = *self.area_cdf.last()?;
+        let choice = random_float() * total_area;
+        let index = self
+            .area_cdf
+            .partition_point(|&area| area <= choice)
+            .min(self.triangles.len() - 1);
+
+        // Picking by area then sampling uniformly is uniform over the whole mesh
+        let sample = self.triangles[index].sample_surface(origin)?;
+        Some(LightSample {
+            pdf: 1.0 / total_area,
+            ..sample
+        })
+    }
+}
@@ -1,9 +1,19 @@
+pub mod bvh;
 pub mod cube;
 pub mod cylinder;
+pub mod mesh;
+pub mod obj;
 pub mod plane;
 pub mod sphere;
+pub mod transform;
+pub mod triangle;
 
+pub use bvh::BvhNode;
 pub use cube::Cube;
 pub use cylinder::Cylinder;
+pub use mesh::Mesh;
+pub use obj::ObjModel;
 pub use plane::Plane;
 pub use sphere::Sphere;
+pub use transform::Transform;
+pub use triangle::Triangle;
@@ -0,0 +1,255 @@
+//! Minimal Wavefront OBJ reader.
+//!
+//! Supports `v`, `vt`, `vn` and `f` statements (with `v`, `v/vt`, `v//vn` and
+//! `v/vt/vn` face vertices, including negative indices). Polygons are
+//! triangulated as fans; everything else (groups, materials, smoothing) is ignored.
+
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use crate::core::{Point3, Vec3};
+
+/// Indices of one face corner into the model's attribute lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjVertex {
+    pub position: usize,
+    pub texcoord: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub positions: Vec<Point3>,
+    pub texcoords: Vec<(f32, f32)>,
+    pub normals: Vec<Vec3>,
+    pub faces: Vec<[ObjVertex; 3]>,
+}
+
+impl ObjModel {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let source = fs::read_to_string(path)?;
+        Self::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut model = ObjModel::default();
+
+        for (line_no, line) in source.lines().enumerate() {
+            let invalid = |msg: &str| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", line_no + 1, msg),
+                )
+            };
+
+            let line = line.split('#').next().unwrap_or("").trim();
+            let mut parts = line.split_whitespace();
+            let Some(keyword) = parts.next() else {
+                continue;
+            };
+
+            match keyword {
+                "v" => {
+                    let [x, y, z] =
+                        parse_floats::<3>(&mut parts).ok_or_else(|| invalid("bad vertex"))?;
+                    model.positions.push(Point3::new(x, y, z));
+                }
+                "vt" => {
+                    let [u, v] =
+                        parse_floats::<2>(&mut parts).ok_or_else(|| invalid("bad texcoord"))?;
+                    model.texcoords.push((u, v));
+                }
+                "vn" => {
+                    let [x, y, z] =
+                        parse_floats::<3>(&mut parts).ok_or_else(|| invalid("bad normal"))?;
+                    model.normals.push(Vec3::new(x, y, z).normalize());
+                }
+                "f" => {
+                    let corners = parts
+                        .map(|corner| model.parse_corner(corner))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| invalid("bad face index"))?;
+                    if corners.len() < 3 {
+                        return Err(invalid("face needs at least three vertices"));
+                    }
+                    for i in 1..corners.len() - 1 {
+                        model.faces.push([corners[0], corners[i], corners[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(model)
+    }
+
+    /// Parse a `v[/vt][/vn]` corner, resolving 1-based and negative indices.
+    fn parse_corner(&self, corner: &str) -> Option<ObjVertex> {
+        let mut fields = corner.split('/');
+        let position = resolve_index(fields.next()?, self.positions.len())?;
+        let texcoord = match fields.next() {
+            Some("") | None => None,
+            Some(s) => Some(resolve_index(s, self.texcoords.len())?),
+        };
+        let normal = match fields.next() {
+            Some("") | None => None,
+            Some(s) => Some(resolve_index(s, self.normals.len())?),
+        };
+
+        Some(ObjVertex {
+            position,
+            texcoord,
+            normal,
+        })
+    }
+}
+
+fn parse_floats<'a, const N: usize>(parts: &mut impl Iterator<Item = &'a str>) -> Option<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in values.iter_mut() {
+        *value = parts.next()?.parse().ok()?;
+    }
+    Some(values)
+}
+
+fn resolve_index(field: &str, len: usize) -> Option<usize> {
+    let index: i64 = field.parse().ok()?;
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+    (0..len as i64)
+        .contains(&resolved)
+        .then_some(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corner(position: usize, texcoord: Option<usize>, normal: Option<usize>) -> ObjVertex {
+        ObjVertex {
+            position,
+            texcoord,
+            normal,
+        }
+    }
+
+    fn error_message(source: &str) -> String {
+        let error = ObjModel::parse(source).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        error.to_string()
+    }
+
+    const SQUARE: &str = "\
+# unit square
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 2
+";
+
+    #[test]
+    fn reads_attributes() {
+        let model = ObjModel::parse(SQUARE).unwrap();
+        assert_eq!(model.positions.len(), 4);
+        assert_eq!(model.positions[2], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(model.texcoords[1], (1.0, 0.0));
+        // Normals are normalized on load
+        assert_eq!(model.normals, vec![Vec3::new(0.0, 0.0, 1.0)]);
+        assert!(model.faces.is_empty());
+    }
+
+    #[test]
+    fn reads_every_corner_form() {
+        let source =
+            format!("{SQUARE}f 1 2 3\nf 1/1 2/2 3/3\nf 1//1 2//1 3//1\nf 1/1/1 2/2/1 3/3/1\n");
+        let model = ObjModel::parse(&source).unwrap();
+        assert_eq!(model.faces.len(), 4);
+        assert_eq!(model.faces[0][1], corner(1, None, None));
+        assert_eq!(model.faces[1][1], corner(1, Some(1), None));
+        assert_eq!(model.faces[2][1], corner(1, None, Some(0)));
+        assert_eq!(model.faces[3][1], corner(1, Some(1), Some(0)));
+    }
+
+    #[test]
+    fn resolves_negative_indices_against_what_is_read_so_far() {
+        let source = format!("{SQUARE}f -4/-4/-1 -3/-3/-1 -1/-1/-1\nv 2 2 2\nf -1 -2 -3\n");
+        let model = ObjModel::parse(&source).unwrap();
+        assert_eq!(
+            model.faces[0],
+            [
+                corner(0, Some(0), Some(0)),
+                corner(1, Some(1), Some(0)),
+                corner(3, Some(3), Some(0)),
+            ]
+        );
+        assert_eq!(
+            model.faces[1],
+            [
+                corner(4, None, None),
+                corner(3, None, None),
+                corner(2, None, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn triangulates_polygons_as_fans() {
+        let source = format!("{SQUARE}v 0.5 1.5 0\nf 1 2 3 5 4\n");
+        let model = ObjModel::parse(&source).unwrap();
+        let positions: Vec<[usize; 3]> = model
+            .faces
+            .iter()
+            .map(|face| face.map(|corner| corner.position))
+            .collect();
+        assert_eq!(positions, vec![[0, 1, 2], [0, 2, 4], [0, 4, 3]]);
+    }
+
+    #[test]
+    fn ignores_comments_and_unknown_statements() {
+        let source = "o thing\ng group\nusemtl red\ns off\nv 0 0 0 # origin\n\n   \nv 1 0 0\nv 0 1 0\nf 1 2 3 # face\n";
+        let model = ObjModel::parse(source).unwrap();
+        assert_eq!(model.positions.len(), 3);
+        assert_eq!(model.faces.len(), 1);
+    }
+
+    #[test]
+    fn reports_malformed_lines_with_their_number() {
+        assert_eq!(error_message("v 0 0 0\nv 1 x 0\n"), "line 2: bad vertex");
+        assert_eq!(error_message("v 0 0\n"), "line 1: bad vertex");
+        assert_eq!(error_message("vt 0\n"), "line 1: bad texcoord");
+        assert_eq!(error_message("vn 0 1\n"), "line 1: bad normal");
+        assert_eq!(
+            error_message(&format!("{SQUARE}f 1 2\n")),
+            "line 11: face needs at least three vertices"
+        );
+    }
+
+    #[test]
+    fn rejects_indices_out_of_range() {
+        // Zero is not a valid OBJ index, and indices may only refer to
+        // attributes that come before the face
+        for face in [
+            "f 0 1 2",
+            "f 1 2 5",
+            "f -5 1 2",
+            "f 1/5 2/1 3/1",
+            "f 1//2 2//1 3//1",
+            "f a 2 3",
+        ] {
+            assert_eq!(
+                error_message(&format!("{SQUARE}{face}\n")),
+                "line 11: bad face index",
+                "{face}"
+            );
+        }
+    }
+}
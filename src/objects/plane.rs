@@ -1,17 +1,20 @@
-use crate::core::{HitRecord, Hittable, Point3, Ray, Vec3};
-use crate::material::Material;
+use std::sync::Arc;
+
+use crate::core::{Aabb, Color, HitRecord, Hittable, LightSample, Point3, Ray, Vec3};
+use crate::material::Scatter;
+use crate::random_float;
 
 #[derive(Clone)]
 pub struct Plane {
     center: Point3, // Bottom-left corner or reference point
     size: Vec3,     // Size in X and Z (Y is ignored)
-    material: Material,
+    material: Arc<dyn Scatter>,
     bounding_box: (Point3, Point3),
 }
 
 impl Plane {
     // Create a new plane centered at 'center' with given 'size' and 'texture'
-    pub fn new(center: Point3, size: Vec3, material: Material) -> Self {
+    pub fn new(center: Point3, size: Vec3, material: impl Into<Arc<dyn Scatter>>) -> Self {
         let half_size = size / 2.0;
 
         let min = Point3::new(
@@ -29,7 +32,7 @@ impl Plane {
         Self {
             center,
             size,
-            material,
+            material: material.into(),
             bounding_box: (min, max),
         }
     }
@@ -53,13 +56,13 @@ impl Plane {
         self.size
     }
 
-    pub fn set_material(&mut self, material: Material) {
-        self.material = material;
+    pub fn set_material(&mut self, material: impl Into<Arc<dyn Scatter>>) {
+        self.material = material.into();
     }
 }
 
 impl Hittable for Plane {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         // Check if ray is parallel to plane (no intersection)
         if ray.direction().y.abs() < 1e-8 {
             return None;
@@ -85,7 +88,6 @@ impl Hittable for Plane {
 
         // Sample the texture
         let (u, v) = self.compute_uv(point);
-        let color = self.material.value_at(u, v);
 
         // Calculate surface properties
         let outward_normal = self.normal();
@@ -95,11 +97,35 @@ impl Hittable for Plane {
             p: point,
             normal,
             t,
-            color,
             u,
             v,
             front_face,
-            material: self.material.clone(),
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let (min, max) = self.bounding_box;
+        Aabb::new(min, max)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.emission().is_some()
+    }
+
+    fn sample_surface(&self, _origin: Point3) -> Option<LightSample> {
+        let (min, max) = self.bounding_box;
+        let p = Point3::new(
+            min.x + random_float() * (max.x - min.x),
+            self.center.y,
+            min.z + random_float() * (max.z - min.z),
+        );
+
+        Some(LightSample {
+            p,
+            normal: self.normal(),
+            pdf: 1.0 / (self.size.x * self.size.z).abs(),
+            emission: self.material.emission().unwrap_or(Color::BLACK),
         })
     }
 }
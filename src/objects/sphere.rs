@@ -1,36 +1,36 @@
-use crate::core::{HitRecord, Hittable, Point3, Ray, Vec3};
-use crate::material::Material;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::core::{Aabb, Color, HitRecord, Hittable, LightSample, Point3, Ray, Vec3};
+use crate::material::Scatter;
+use crate::random_float;
 
 #[derive(Clone)]
 pub struct Sphere {
     center: Point3,
     radius: f32,
-    material: Material,
+    material: Arc<dyn Scatter>,
     bounding_box: (Point3, Point3),
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f32, material: Material) -> Self {
+    pub fn new(center: Point3, radius: f32, material: impl Into<Arc<dyn Scatter>>) -> Self {
         let rvec = Vec3::new(radius, radius, radius);
         Self {
             center,
             radius,
-            material,
+            material: material.into(),
             bounding_box: (center - rvec, center + rvec),
         }
     }
 
-    fn bounding_box(&self) -> (Point3, Point3) {
-        self.bounding_box
-    }
-
     fn compute_normal(&self, point: Point3) -> Vec3 {
         // Normal at any point on sphere surface is (point - center) / radius
         (point - self.center) / self.radius
     }
 
     fn compute_uv(&self, point: Point3) -> (f32, f32) {
-        let (min, max) = self.bounding_box();
+        let (min, max) = self.bounding_box;
         let u = (point.x() - min.x()) / (max.x() - min.x());
         let v = (point.y() - min.y()) / (max.y() - min.y());
         (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))
@@ -38,7 +38,7 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         // Vector from ray origin to sphere center
         let oc = ray.origin() - self.center;
 
@@ -70,17 +70,72 @@ impl Hittable for Sphere {
         let outward_normal = self.compute_normal(point);
         let (normal, front_face) = HitRecord::face_normal(ray, outward_normal);
         let (u, v) = self.compute_uv(point);
-        let color = self.material.value_at(u, v);
 
         Some(HitRecord {
             p: point,
             normal,
             t,
-            color,
             u,
             v,
             front_face,
-            material: self.material.clone(),
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // Negative radii (hollow spheres) flip the stored corners, so sort them
+        let (a, b) = self.bounding_box;
+        Aabb::from_points(a, b)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.emission().is_some()
+    }
+
+    fn sample_surface(&self, origin: Point3) -> Option<LightSample> {
+        let radius = self.radius.abs();
+        let emission = self.material.emission().unwrap_or(Color::BLACK);
+        let to_center = self.center - origin;
+        let dist_squared = to_center.length_squared();
+
+        // From inside the sphere every point is visible: sample the whole surface
+        if dist_squared <= radius * radius {
+            let normal = Vec3::random_unit_vector();
+            return Some(LightSample {
+                p: self.center + normal * radius,
+                normal,
+                pdf: 1.0 / (4.0 * PI * radius * radius),
+                emission,
+            });
+        }
+
+        // Otherwise sample directions uniformly inside the cone the sphere subtends
+        let cos_max = (1.0 - radius * radius / dist_squared).sqrt();
+        let cos_theta = 1.0 + random_float() * (cos_max - 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_float();
+
+        let w = to_center / dist_squared.sqrt();
+        let (u, v) = w.tangent_frame();
+        let direction = u * (phi.cos() * sin_theta) + v * (phi.sin() * sin_theta) + w * cos_theta;
+
+        // Nearest intersection of the sampled direction with the sphere
+        let oc = origin - self.center;
+        let half_b = oc.dot(direction);
+        let c = oc.length_squared() - radius * radius;
+        let t = -half_b - (half_b * half_b - c).max(0.0).sqrt();
+
+        let p = origin + direction * t;
+        let normal = (p - self.center) / radius;
+        let cos_light = normal.dot(-direction).abs().max(1e-6);
+
+        // Convert the solid-angle density of the cone into an area density
+        let solid_angle_pdf = 1.0 / (2.0 * PI * (1.0 - cos_max));
+        Some(LightSample {
+            p,
+            normal,
+            pdf: solid_angle_pdf * cos_light / (t * t),
+            emission,
         })
     }
 }
@@ -0,0 +1,87 @@
+use glam::{Affine3A, Mat3A};
+
+use crate::core::{Aabb, HitRecord, Hittable, LightSample, Point3, Ray, Vec3};
+
+/// Wraps any hittable with an affine transform (translation, rotation, scale).
+///
+/// Rays are moved into the object's own space for intersection, and the hit
+/// point and normal are moved back into world space.
+pub struct Transform {
+    object: Box<dyn Hittable>,
+    to_world: Affine3A,
+    to_object: Affine3A,
+    normal_matrix: Mat3A,
+    bbox: Aabb,
+}
+
+impl Transform {
+    pub fn new(object: Box<dyn Hittable>, to_world: Affine3A) -> Self {
+        let to_object = to_world.inverse();
+        // Normals transform with the inverse transpose to stay perpendicular under non-uniform scale
+        let normal_matrix = to_object.matrix3.transpose();
+
+        let Aabb { min, max } = object.bounding_box();
+        let bbox = (0..8)
+            .map(|i| {
+                let corner = Point3::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                );
+                let p = to_world.transform_point3(corner.into()).into();
+                Aabb::new(p, p)
+            })
+            .reduce(Aabb::surrounding)
+            .unwrap();
+
+        Self {
+            object,
+            to_world,
+            to_object,
+            normal_matrix,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        // The direction is not renormalized, so t is the same in both spaces
+        let local_ray = Ray::new(
+            self.to_object.transform_point3(ray.origin().into()).into(),
+            self.to_object
+                .transform_vector3(ray.direction().into())
+                .into(),
+        );
+
+        let mut hit = self.object.hit(&local_ray, t_min, t_max)?;
+        hit.p = self.to_world.transform_point3(hit.p.into()).into();
+        hit.normal = Vec3::from(self.normal_matrix.mul_vec3(hit.normal.into())).normalize();
+
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    fn sample_surface(&self, origin: Point3) -> Option<LightSample> {
+        let local_origin = self.to_object.transform_point3(origin.into()).into();
+        let sample = self.object.sample_surface(local_origin)?;
+
+        // A surface element with unit normal n is stretched by |det M| * |M^-T n|
+        let normal = Vec3::from(self.normal_matrix.mul_vec3(sample.normal.into()));
+        let area_scale = self.to_world.matrix3.determinant().abs() * normal.length();
+
+        Some(LightSample {
+            p: self.to_world.transform_point3(sample.p.into()).into(),
+            normal: normal.normalize(),
+            pdf: sample.pdf / area_scale,
+            emission: sample.emission,
+        })
+    }
+}
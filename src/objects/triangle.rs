@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use crate::core::{Aabb, Color, HitRecord, Hittable, LightSample, Point3, Ray, Vec3};
+use crate::material::Scatter;
+use crate::random_float;
+
+#[derive(Clone)]
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,   // Per-vertex normals for smooth shading
+    uvs: Option<[(f32, f32); 3]>, // Per-vertex texture coordinates
+    material: Arc<dyn Scatter>,
+}
+
+impl Triangle {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: impl Into<Arc<dyn Scatter>>) -> Self {
+        Self {
+            vertices: [v0, v1, v2],
+            normals: None,
+            uvs: None,
+            material: material.into(),
+        }
+    }
+
+    /// Interpolate these normals across the face instead of using the flat face normal.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Interpolate these texture coordinates instead of using the raw barycentrics.
+    pub fn with_uvs(mut self, uvs: [(f32, f32); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    pub fn vertices(&self) -> [Point3; 3] {
+        self.vertices
+    }
+
+    pub fn area(&self) -> f32 {
+        let [v0, v1, v2] = self.vertices;
+        (v1 - v0).cross(v2 - v0).length() / 2.0
+    }
+
+    pub fn set_material(&mut self, material: impl Into<Arc<dyn Scatter>>) {
+        self.material = material.into();
+    }
+
+    fn compute_normal(&self, b1: f32, b2: f32) -> Vec3 {
+        let [v0, v1, v2] = self.vertices;
+        match self.normals {
+            Some([n0, n1, n2]) => ((1.0 - b1 - b2) * n0 + b1 * n1 + b2 * n2).normalize(),
+            None => (v1 - v0).cross(v2 - v0).normalize(),
+        }
+    }
+
+    fn compute_uv(&self, b1: f32, b2: f32) -> (f32, f32) {
+        match self.uvs {
+            Some([(u0, v0), (u1, v1), (u2, v2)]) => {
+                let b0 = 1.0 - b1 - b2;
+                (b0 * u0 + b1 * u1 + b2 * u2, b0 * v0 + b1 * v1 + b2 * v2)
+            }
+            None => (b1, b2),
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        // Möller–Trumbore: solve for t and the barycentric coordinates (b1, b2)
+        let [v0, v1, v2] = self.vertices;
+        let edge1 = v1 - v0;
+        let edge2 = v2 - v0;
+
+        let pvec = ray.direction().cross(edge2);
+        let det = edge1.dot(pvec);
+        if det.abs() < 1e-8 {
+            return None; // Ray is parallel to the triangle
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = ray.origin() - v0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let qvec = tvec.cross(edge1);
+        let b2 = ray.direction().dot(qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(qvec) * inv_det;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let outward_normal = self.compute_normal(b1, b2);
+        let (normal, front_face) = HitRecord::face_normal(ray, outward_normal);
+        let (u, v) = self.compute_uv(b1, b2);
+
+        Some(HitRecord {
+            p: point,
+            normal,
+            t,
+            u,
+            v,
+            front_face,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [v0, v1, v2] = self.vertices;
+        let bbox = Aabb::surrounding(Aabb::from_points(v0, v1), Aabb::from_points(v1, v2));
+
+        // Pad axis-aligned triangles so their box never has zero thickness
+        let pad = Vec3::splat(1e-4);
+        Aabb::new(bbox.min - pad, bbox.max + pad)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.emission().is_some()
+    }
+
+    fn sample_surface(&self, _origin: Point3) -> Option<LightSample> {
+        let [v0, v1, v2] = self.vertices;
+
+        // Uniform barycentric coordinates via the square-root warp
+        let su = random_float().sqrt();
+        let b1 = 1.0 - su;
+        let b2 = random_float() * su;
+        let p = (1.0 - b1 - b2) * v0 + b1 * v1 + b2 * v2;
+
+        Some(LightSample {
+            p,
+            normal: (v1 - v0).cross(v2 - v0).normalize(),
+            pdf: 1.0 / self.area(),
+            emission: self.material.emission().unwrap_or(Color::BLACK),
+        })
+    }
+}
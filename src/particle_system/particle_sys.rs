@@ -1,4 +1,5 @@
 use crate::core::{Hittable, Point3};
+use crate::random_range;
 
 pub struct ParticleSys<F>
 where
@@ -25,11 +26,9 @@ where
         }
     }
 
-    /// Generate all particle objects ensuring no overlapping within min_dist
+    /// Generate all particle objects ensuring no overlapping within min_dist.
+    /// Positions come from the crate's RNG; call `seed_rng` first for a repeatable layout.
     pub fn generate(&self) -> Vec<Box<dyn Hittable>> {
-        use rand::Rng;
-        let mut rng = rand::rng();
-
         let mut particles = Vec::with_capacity(self.count);
         let mut points: Vec<Point3> = Vec::with_capacity(self.count);
 
@@ -38,9 +37,9 @@ where
         while points.len() < self.count {
             let mut attempts = 0;
             let mut candidate = Point3::new(
-                rng.random_range(self.min.x()..self.max.x()),
-                rng.random_range(self.min.y()..self.max.y()),
-                rng.random_range(self.min.z()..self.max.z()),
+                random_range(self.min.x()..self.max.x()),
+                random_range(self.min.y()..self.max.y()),
+                random_range(self.min.z()..self.max.z()),
             );
 
             // Retry until candidate is far enough from all others, or max_attempts reached
@@ -48,9 +47,9 @@ where
                 && attempts < max_attempts
             {
                 candidate = Point3::new(
-                    rng.random_range(self.min.x()..self.max.x()),
-                    rng.random_range(self.min.y()..self.max.y()),
-                    rng.random_range(self.min.z()..self.max.z()),
+                    random_range(self.min.x()..self.max.x()),
+                    random_range(self.min.y()..self.max.y()),
+                    random_range(self.min.z()..self.max.z()),
                 );
                 attempts += 1;
             }
@@ -1,8 +1,10 @@
-use image::ImageReader;
+use image::{ImageReader, Rgb32FImage, RgbImage};
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::path::Path;
 
 use crate::core::color::*;
+use crate::pixels::tone_mapping::ToneMapping;
 
 #[derive(Debug, Clone)]
 pub struct Image {
@@ -29,6 +31,48 @@ impl Image {
         }
     }
 
+    /// Scale every pixel by `2^exposure` and then apply `tone_mapping`.
+    pub fn tone_map(&mut self, tone_mapping: ToneMapping, exposure: f32) {
+        let scale = exposure.exp2();
+        for pixel in &mut self.pixels {
+            *pixel = tone_mapping.apply(*pixel * scale);
+        }
+    }
+
+    /// Save in the format given by the file extension: `.exr` and `.hdr` keep
+    /// the linear, unclamped colors, `.ppm` (or no extension) writes ASCII PPM,
+    /// and anything else the `image` crate can encode (`.png`, `.jpg`, ...)
+    /// is written gamma corrected at 8 bits per channel.
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            None | Some("ppm") => self.save_ppm(path),
+            Some("exr") | Some("hdr") => self
+                .to_rgb32f()
+                .save(path)
+                .map_err(std::io::Error::other),
+            Some(_) => self.to_rgb8().save(path).map_err(std::io::Error::other),
+        }
+    }
+
+    fn to_rgb8(&self) -> RgbImage {
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let (r, g, b) = self.get_pixel(x as usize, y as usize).to_u8_tuple(true);
+            image::Rgb([r, g, b])
+        })
+    }
+
+    fn to_rgb32f(&self) -> Rgb32FImage {
+        Rgb32FImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let color = self.get_pixel(x as usize, y as usize);
+            image::Rgb([color.r, color.g, color.b])
+        })
+    }
+
     pub fn save_ppm(&self, path: &str) -> std::io::Result<()> {
         let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
@@ -44,12 +88,12 @@ impl Image {
         Ok(())
     }
 
-    pub fn load(path: &str) -> std::io::Result<Self> {
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
         // Load the image using the image crate
         let img = ImageReader::open(path)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .map_err(std::io::Error::other)?
             .decode()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .map_err(std::io::Error::other)?
             .to_rgb8(); // Convert to RGB
 
         let (width, height) = img.dimensions();
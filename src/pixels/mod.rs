@@ -1,5 +1,9 @@
 pub mod image;
+pub mod sample_buffer;
 pub mod texture;
+pub mod tone_mapping;
 
 pub use image::Image;
+pub use sample_buffer::SampleBuffer;
 pub use texture::Texture;
+pub use tone_mapping::ToneMapping;
@@ -0,0 +1,121 @@
+use crate::core::color::Color;
+use crate::pixels::image::Image;
+
+/// Gamma the image is displayed with, for judging how visible noise is.
+const DISPLAY_GAMMA: f32 = 2.2;
+
+/// Running sum of the samples taken for each pixel of a render, so a render
+/// can be continued with more samples later.
+#[derive(Debug, Clone)]
+pub struct SampleBuffer {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) sums: Vec<Color>,
+    /// Sum of the squared luminance of the samples, for the variance
+    pub(crate) sq_sums: Vec<f32>,
+    pub(crate) counts: Vec<u32>,
+}
+
+impl SampleBuffer {
+    /// An empty buffer: no samples have been taken for any pixel yet.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sums: vec![Color::BLACK; width * height],
+            sq_sums: vec![0.0; width * height],
+            counts: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of samples taken for the pixel.
+    pub fn samples(&self, x: usize, y: usize) -> u32 {
+        self.counts[y * self.width + x]
+    }
+
+    /// Fewest samples taken for any pixel.
+    pub fn min_samples(&self) -> u32 {
+        self.counts.iter().copied().min().unwrap_or(0)
+    }
+
+    /// Samples per pixel, averaged over the image.
+    pub fn mean_samples(&self) -> f32 {
+        let total: u64 = self.counts.iter().map(|&count| count as u64).sum();
+        total as f32 / self.counts.len().max(1) as f32
+    }
+
+    /// How far the pixel's brightness may still be from where more samples
+    /// would take it: the standard error of its mean luminance, scaled to the
+    /// 0 to 1 range of the gamma corrected image, where noise is judged. A
+    /// pixel with fewer than two samples has no estimate and is infinitely noisy.
+    pub fn error(&self, x: usize, y: usize) -> f32 {
+        self.pixel_error(y * self.width + x)
+    }
+
+    pub(crate) fn pixel_error(&self, index: usize) -> f32 {
+        let count = self.counts[index];
+        if count < 2 {
+            return f32::INFINITY;
+        }
+        let n = count as f32;
+        let mean = self.sums[index].luminance() / n;
+        let variance = (self.sq_sums[index] / n - mean * mean).max(0.0) * n / (n - 1.0);
+        let std_error = (variance / n).sqrt();
+
+        // Slope of the gamma curve at the mean: the same noise is far more
+        // visible in the shadows than in the highlights
+        let slope = mean.max(1e-3).powf(1.0 / DISPLAY_GAMMA - 1.0) / DISPLAY_GAMMA;
+        std_error * slope
+    }
+
+    /// Image of how many samples each pixel took, running from black through
+    /// blue, red and yellow to white at the most any pixel took.
+    pub fn heatmap(&self) -> Image {
+        let ramp = [
+            Color::BLACK,
+            Color::BLUE,
+            Color::RED,
+            Color::YELLOW,
+            Color::WHITE,
+        ];
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1);
+
+        let mut image = Image::new(self.width, self.height);
+        image.pixels = self
+            .counts
+            .iter()
+            .map(|&count| {
+                let t = count as f32 / max as f32 * (ramp.len() - 1) as f32;
+                let i = (t as usize).min(ramp.len() - 2);
+                Color::lerp(ramp[i], ramp[i + 1], t - i as f32)
+            })
+            .collect();
+        image
+    }
+
+    /// Average of each pixel's samples; pixels without any are black.
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        image.pixels = self
+            .sums
+            .iter()
+            .zip(&self.counts)
+            .map(|(&sum, &count)| {
+                if count == 0 {
+                    Color::BLACK
+                } else {
+                    sum / count as i32
+                }
+            })
+            .collect();
+        image
+    }
+}
@@ -11,11 +11,13 @@ pub enum Texture {
     Image(Arc<Image>),
 }
 
-impl Texture {
-    pub fn default() -> Self {
+impl Default for Texture {
+    fn default() -> Self {
         Texture::SolidColor(Color::BLACK)
     }
+}
 
+impl Texture {
     #[inline]
     pub fn value_at(&self, u: f32, v: f32) -> Color {
         match self {
@@ -0,0 +1,32 @@
+use crate::core::color::Color;
+
+/// Curve that compresses the unbounded light a render collects into the
+/// 0 to 1 range of a displayable image.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ToneMapping {
+    /// Colors are kept as they are; anything above 1 is clipped when written to 8-bit formats
+    #[default]
+    None,
+    /// `c / (1 + c)`: rolls highlights off gently and never quite reaches white
+    Reinhard,
+    /// Filmic curve fitted to the ACES reference transform, with more contrast
+    Aces,
+}
+
+impl ToneMapping {
+    pub fn apply(self, color: Color) -> Color {
+        let curve: fn(f32) -> f32 = match self {
+            ToneMapping::None => return color,
+            ToneMapping::Reinhard => |x| x / (1.0 + x),
+            // Krzysztof Narkowicz's fit
+            ToneMapping::Aces => |x| {
+                ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+            },
+        };
+        Color::new(
+            curve(color.r.max(0.0)),
+            curve(color.g.max(0.0)),
+            curve(color.b.max(0.0)),
+        )
+    }
+}
@@ -1,4 +1,21 @@
+use std::f32::consts::PI;
+
 use crate::core::*;
+use crate::random_float;
+
+/// How directions are mapped onto the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Pinhole frustum set by the vertical field of view
+    Perspective,
+    /// Parallel rays; `width` is the size of the view in scene units
+    Orthographic { width: f32 },
+    /// Equidistant fisheye; `fov` is the angle in degrees across the image width
+    /// and may exceed 180. Corners more than 180° off the view direction are black
+    Fisheye { fov: f32 },
+    /// Full 360° by 180° panorama centered on the view direction; best with a 2:1 resolution
+    Equirectangular,
+}
 
 #[derive(Debug)]
 pub struct Camera {
@@ -8,12 +25,33 @@ pub struct Camera {
     vfov: f32,
     focal_length: f32,
     aspect_ratio: f32,
+    fixed_aspect_ratio: Option<f32>, // None follows the resolution
     resolution: (u32, u32),
+    projection: Projection,
+
+    // thin lens; an aperture of 0 is a pinhole camera
+    aperture: f32,
+    focus_distance: Option<f32>, // None focuses on `look_at`
+    blades: u32,                 // fewer than 3 gives a round aperture
+
+    // nothing nearer than `near` or farther than `far` is seen
+    near: f32,
+    far: Option<f32>, // None sees to infinity
 
     // precompute variables for direction calculation
     lower_left_corner: Point3,
     horizontal: Vec3,
     vertical: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    focus: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Camera {
@@ -25,10 +63,21 @@ impl Camera {
             vfov: 60.0,
             focal_length: 1.0,
             aspect_ratio: 600.0 / 400.0,
+            fixed_aspect_ratio: None,
             resolution: (600, 400),
+            projection: Projection::Perspective,
+            aperture: 0.0,
+            focus_distance: None,
+            blades: 0,
+            near: 0.0,
+            far: None,
             lower_left_corner: Point3::ZERO,
             horizontal: Vec3::ZERO,
             vertical: Vec3::ZERO,
+            u: Vec3::ZERO,
+            v: Vec3::ZERO,
+            w: Vec3::ZERO,
+            focus: 1.0,
         };
         camera.update_viewport();
         camera
@@ -38,6 +87,10 @@ impl Camera {
         self.resolution
     }
 
+    /// Place the camera at `origin`, looking at `look_at` with `vup` pointing
+    /// up, and seeing `vfov` degrees from the bottom of the image to the top.
+    /// The image plane is `focal_length` away and sized to match, so the
+    /// focal length does not change the image.
     pub fn set(
         &mut self,
         origin: Point3,
@@ -53,17 +106,73 @@ impl Camera {
         self.vfov = vfov;
         self.focal_length = focal_length;
         self.resolution = resolution;
-        self.aspect_ratio = resolution.0 as f32 / resolution.1 as f32;
+        self.update_aspect_ratio();
 
         self.update_viewport();
     }
 
     pub fn set_resolution(&mut self, resolution: (u32, u32)) {
         self.resolution = resolution;
-        self.aspect_ratio = resolution.0 as f32 / resolution.1 as f32;
+        self.update_aspect_ratio();
+        self.update_viewport();
+    }
+
+    /// Stretch the view to `aspect_ratio` (width over height) regardless of the
+    /// resolution, giving non-square pixels. `None` keeps pixels square.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: Option<f32>) {
+        self.fixed_aspect_ratio = aspect_ratio.filter(|ratio| *ratio > 0.0);
+        self.update_aspect_ratio();
+        self.update_viewport();
+    }
+
+    fn update_aspect_ratio(&mut self) {
+        self.aspect_ratio = self
+            .fixed_aspect_ratio
+            .unwrap_or(self.resolution.0 as f32 / self.resolution.1 as f32);
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.update_viewport();
+    }
+
+    /// Give the camera a lens of diameter `aperture`, sharp at `focus_distance`
+    /// (or at the `look_at` point when `None`). With 3 or more `blades` the
+    /// aperture is a regular polygon, which shapes out-of-focus highlights.
+    pub fn set_lens(&mut self, aperture: f32, focus_distance: Option<f32>, blades: u32) {
+        self.aperture = aperture.max(0.0);
+        self.focus_distance = focus_distance;
+        self.blades = blades;
         self.update_viewport();
     }
 
+    /// Hide everything nearer than `near` or farther than `far` (unbounded
+    /// when `None`). Perspective and orthographic cameras clip at planes facing
+    /// the view direction; fisheye and equirectangular ones, which see to the
+    /// sides and behind, clip at that distance from the camera in every direction.
+    pub fn set_clip(&mut self, near: f32, far: Option<f32>) {
+        self.near = near.max(0.0);
+        self.far = far;
+    }
+
+    /// Range of distances along `ray`, one made by `generate_ray`, in which
+    /// hits are seen, measured in lengths of its direction.
+    pub fn clip_range(&self, ray: &Ray) -> (f32, f32) {
+        let scale = match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                ray.direction().dot(-self.w)
+            }
+            Projection::Fisheye { .. } | Projection::Equirectangular => ray.direction().length(),
+        };
+        if scale <= 0.0 {
+            return (0.0, f32::INFINITY);
+        }
+        (
+            self.near / scale,
+            self.far.map_or(f32::INFINITY, |far| far / scale),
+        )
+    }
+
     pub fn update_viewport(&mut self) {
         let theta = self.vfov.to_radians();
         let viewport_height = 2.0 * (theta / 2.0).tan();
@@ -73,20 +182,170 @@ impl Camera {
         let u = self.vup.cross(w).normalize();
         let v = w.cross(u);
 
-        self.horizontal = u * viewport_width * self.focal_length;
-        self.vertical = v * viewport_height * self.focal_length;
+        if let Projection::Orthographic { width } = self.projection {
+            // The view rectangle sits at the camera position itself
+            self.horizontal = u * width;
+            self.vertical = v * (width / self.aspect_ratio);
+            self.lower_left_corner = self.origin - self.horizontal / 2.0 - self.vertical / 2.0;
+        } else {
+            self.horizontal = u * viewport_width * self.focal_length;
+            self.vertical = v * viewport_height * self.focal_length;
+            self.lower_left_corner =
+                self.origin - self.horizontal / 2.0 - self.vertical / 2.0 - w * self.focal_length;
+        }
+
+        self.u = u;
+        self.v = v;
+        self.w = w;
+        self.focus = self
+            .focus_distance
+            .unwrap_or_else(|| self.origin.distance(self.look_at));
+    }
 
-        self.lower_left_corner =
-            self.origin - self.horizontal / 2.0 - self.vertical / 2.0 - w * self.focal_length;
+    /// Whether the camera sees anything at these offsets. The corners of a
+    /// wide fisheye image lie more than 180° from the view direction, past
+    /// looking straight back, and see nothing.
+    pub fn in_view(&self, horizontal_offset: f32, vertical_offset: f32) -> bool {
+        match self.projection {
+            Projection::Fisheye { fov } => {
+                let (theta, _) = self.fisheye_angles(horizontal_offset, vertical_offset, fov);
+                theta <= PI
+            }
+            _ => true,
+        }
     }
 
+    /// Ray through the image at the given offsets, 0 to 1 from left to right
+    /// and from bottom to top. Fisheye offsets that are not `in_view` give the
+    /// ray looking straight back.
     pub fn generate_ray(&self, horizontal_offset: f32, vertical_offset: f32) -> Ray {
+        let forward = -self.w;
         let point_on_plane = self.lower_left_corner
             + self.horizontal * horizontal_offset
             + self.vertical * vertical_offset;
-        let direction = point_on_plane - self.origin;
 
-        Ray::new(self.origin, direction)
+        let (origin, direction) = match self.projection {
+            Projection::Perspective => (self.origin, point_on_plane - self.origin),
+            Projection::Orthographic { .. } => (point_on_plane, forward),
+            Projection::Fisheye { fov } => {
+                let (theta, phi) = self.fisheye_angles(horizontal_offset, vertical_offset, fov);
+                let theta = theta.min(PI);
+                let sideways = self.u * phi.cos() + self.v * phi.sin();
+                (self.origin, sideways * theta.sin() + forward * theta.cos())
+            }
+            Projection::Equirectangular => {
+                let longitude = (horizontal_offset - 0.5) * 2.0 * PI;
+                let latitude = (vertical_offset - 0.5) * PI;
+                let direction = self.u * (latitude.cos() * longitude.sin())
+                    + self.v * latitude.sin()
+                    + forward * (latitude.cos() * longitude.cos());
+                (self.origin, direction)
+            }
+        };
+
+        if self.aperture <= 0.0 {
+            return Ray::new(origin, direction);
+        }
+
+        // Every ray through the lens meets the pinhole ray where it reaches the
+        // focus distance: on a plane for flat projections, on a sphere for wide ones
+        let focus_point = match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                origin + direction * (self.focus / direction.dot(forward))
+            }
+            Projection::Fisheye { .. } | Projection::Equirectangular => {
+                origin + direction.normalize() * self.focus
+            }
+        };
+        let (lens_x, lens_y) = self.sample_aperture();
+        let lens_origin = origin + (self.u * lens_x + self.v * lens_y) * (self.aperture / 2.0);
+
+        Ray::new(lens_origin, focus_point - lens_origin)
+    }
+
+    /// Angle from the view direction and angle around it of a fisheye ray.
+    fn fisheye_angles(&self, horizontal_offset: f32, vertical_offset: f32, fov: f32) -> (f32, f32) {
+        // Distance from the image center, in half-widths, is proportional to the angle
+        let x = 2.0 * horizontal_offset - 1.0;
+        let y = (2.0 * vertical_offset - 1.0) / self.aspect_ratio;
+        let theta = (x * x + y * y).sqrt() * fov.to_radians() / 2.0;
+        (theta, y.atan2(x))
+    }
+
+    /// Where `point` appears in the image, as the offsets `generate_ray` takes:
+    /// 0 to 1 from left to right and from bottom to top. Ignores the lens.
+    /// Returns `None` for points the camera cannot see at any offset.
+    pub fn project(&self, point: Point3) -> Option<(f32, f32)> {
+        let forward = -self.w;
+        let offsets_on_plane = |on_plane: Point3| {
+            let from_corner = on_plane - self.lower_left_corner;
+            (
+                from_corner.dot(self.horizontal) / self.horizontal.length_squared(),
+                from_corner.dot(self.vertical) / self.vertical.length_squared(),
+            )
+        };
+
+        let to_point = point - self.origin;
+        let (x, y, z) = (
+            to_point.dot(self.u),
+            to_point.dot(self.v),
+            to_point.dot(forward),
+        );
+        match self.projection {
+            Projection::Perspective => {
+                if z <= 0.0 {
+                    return None;
+                }
+                Some(offsets_on_plane(
+                    self.origin + to_point * (self.focal_length / z),
+                ))
+            }
+            Projection::Orthographic { .. } => {
+                if z < 0.0 {
+                    return None;
+                }
+                Some(offsets_on_plane(point - forward * z))
+            }
+            Projection::Fisheye { fov } => {
+                // The corners of the image reach beyond `fov`, so nothing is culled
+                let theta = (x * x + y * y).sqrt().atan2(z);
+                let radius = theta / (fov.to_radians() / 2.0);
+                let phi = y.atan2(x);
+                Some((
+                    (radius * phi.cos() + 1.0) / 2.0,
+                    (radius * phi.sin() * self.aspect_ratio + 1.0) / 2.0,
+                ))
+            }
+            Projection::Equirectangular => {
+                let longitude = x.atan2(z);
+                let latitude = y.atan2((x * x + z * z).sqrt());
+                Some((longitude / (2.0 * PI) + 0.5, latitude / PI + 0.5))
+            }
+        }
+    }
+
+    /// Uniform point on the unit-radius aperture shape.
+    fn sample_aperture(&self) -> (f32, f32) {
+        if self.blades < 3 {
+            let r = random_float().sqrt();
+            let theta = 2.0 * PI * random_float();
+            return (r * theta.cos(), r * theta.sin());
+        }
+
+        // Pick one of the equal triangles fanning out from the center, then a point in it
+        let blades = self.blades as f32;
+        let blade = (random_float() * blades).floor().min(blades - 1.0);
+        let corner = |i: f32| {
+            let angle = PI / 2.0 + 2.0 * PI * i / blades;
+            (angle.cos(), angle.sin())
+        };
+        let (ax, ay) = corner(blade);
+        let (bx, by) = corner(blade + 1.0);
+
+        let su = random_float().sqrt();
+        let b1 = su * (1.0 - random_float());
+        let b2 = su - b1;
+        (b1 * ax + b2 * bx, b1 * ay + b2 * by)
     }
 
     pub fn generate_rays(&self) -> Vec<Vec<Ray>> {
@@ -110,3 +369,65 @@ impl Camera {
         rays
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(focal_length: f32) -> Camera {
+        let mut camera = Camera::new();
+        camera.set(
+            Point3::new(1.0, 2.0, 4.0),
+            Point3::new(0.0, 0.5, 0.0),
+            Vec3::Y,
+            50.0,
+            focal_length,
+            (300, 200),
+        );
+        camera
+    }
+
+    #[test]
+    fn focal_length_does_not_change_the_view() {
+        let reference = camera(1.0);
+        for focal_length in [0.2, 1.5, 35.0] {
+            let camera = camera(focal_length);
+            for (x, y) in [(0.0, 0.0), (0.5, 0.5), (0.9, 0.2), (1.0, 1.0)] {
+                let expected = reference.generate_ray(x, y);
+                let ray = camera.generate_ray(x, y);
+                assert_eq!(ray.origin(), expected.origin());
+                let difference = ray.direction().normalize() - expected.direction().normalize();
+                assert!(difference.length() < 1e-5, "{focal_length} at {x}, {y}");
+
+                let point = expected.at(7.0);
+                let (px, py) = camera.project(point).unwrap();
+                assert!((px - x).abs() < 1e-4 && (py - y).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn wide_fisheye_corners_see_nothing() {
+        // The corners are 1.25 half-widths from the center, 187.5° off the view
+        let mut camera = camera(1.0);
+        camera.set_projection(Projection::Fisheye { fov: 300.0 });
+        let forward = -camera.w;
+
+        assert!(camera.in_view(0.5, 0.5));
+        assert!(camera.in_view(0.0, 0.5));
+        let edge = camera.generate_ray(0.0, 0.5).direction().normalize();
+        assert!((edge.dot(forward) - 150f32.to_radians().cos()).abs() < 1e-5);
+
+        for (x, y) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+            assert!(!camera.in_view(x, y), "corner {x}, {y}");
+            // Straight back, not wrapped around past it
+            let direction = camera.generate_ray(x, y).direction().normalize();
+            assert!(direction.dot(forward) < -0.9999, "corner {x}, {y}");
+        }
+
+        camera.set_projection(Projection::Fisheye { fov: 180.0 });
+        assert!(camera.in_view(0.0, 0.0));
+        camera.set_projection(Projection::Perspective);
+        assert!(camera.in_view(0.0, 0.0));
+    }
+}
@@ -0,0 +1,240 @@
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::core::color::Color;
+use crate::pixels::SampleBuffer;
+
+/// Start of every checkpoint file, including the version of its layout.
+const MAGIC: &[u8; 8] = b"rt2ckpt2";
+
+/// The samples of an unfinished render, saved so it can be continued later.
+///
+/// The file holds the sum, the sum of squared luminance and the number of
+/// samples of every pixel, which is all `Scene::render_progressive` needs to pick up where it left off.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    /// Hash of the scene and settings the samples were taken with, from `SceneHasher`
+    pub scene_hash: u64,
+    pub seed: u64,
+    pub buffer: SampleBuffer,
+}
+
+impl Checkpoint {
+    /// Write the checkpoint to `path`. It is written to a temporary file first,
+    /// so a render killed while saving keeps its previous checkpoint.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".partial");
+        let mut writer = BufWriter::new(File::create(&temp)?);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&self.scene_hash.to_le_bytes())?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&(self.buffer.width as u32).to_le_bytes())?;
+        writer.write_all(&(self.buffer.height as u32).to_le_bytes())?;
+        let buffer = &self.buffer;
+        for ((sum, sq_sum), count) in buffer.sums.iter().zip(&buffer.sq_sums).zip(&buffer.counts) {
+            for value in [sum.r, sum.g, sum.b, *sq_sum] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.write_all(&count.to_le_bytes())?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+
+        fs::rename(&temp, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a render checkpoint",
+            ));
+        }
+        let scene_hash = read_u64(&mut reader)?;
+        let seed = read_u64(&mut reader)?;
+        let width = read_u32(&mut reader)? as usize;
+        let height = read_u32(&mut reader)? as usize;
+
+        let mut buffer = SampleBuffer::new(width, height);
+        let pixels = buffer
+            .sums
+            .iter_mut()
+            .zip(&mut buffer.sq_sums)
+            .zip(&mut buffer.counts);
+        for ((sum, sq_sum), count) in pixels {
+            *sum = Color::new(
+                read_f32(&mut reader)?,
+                read_f32(&mut reader)?,
+                read_f32(&mut reader)?,
+            );
+            *sq_sum = read_f32(&mut reader)?;
+            *count = read_u32(&mut reader)?;
+        }
+
+        Ok(Self {
+            scene_hash,
+            seed,
+            buffer,
+        })
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    Ok(f32::from_bits(read_u32(reader)?))
+}
+
+/// FNV-1a, for telling whether a checkpoint was made from the same scene.
+/// Unlike the standard library's hasher it gives the same result in every
+/// build, so checkpoints stay valid after the renderer is recompiled.
+#[derive(Debug, Clone)]
+pub struct SceneHasher(u64);
+
+impl Default for SceneHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for SceneHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn checkpoint() -> Checkpoint {
+        let mut buffer = SampleBuffer::new(3, 2);
+        for (i, ((sum, sq_sum), count)) in buffer
+            .sums
+            .iter_mut()
+            .zip(&mut buffer.sq_sums)
+            .zip(&mut buffer.counts)
+            .enumerate()
+        {
+            *sum = Color::new(i as f32, 0.5 / (i + 1) as f32, f32::MAX);
+            *sq_sum = i as f32 * 0.25;
+            *count = i as u32 * 7;
+        }
+        Checkpoint {
+            scene_hash: 0x0123_4567_89ab_cdef,
+            seed: u64::MAX - 1,
+            buffer,
+        }
+    }
+
+    /// A file name of its own in the temporary directory for each test.
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rt_2-{name}-{}.ckpt", std::process::id()))
+    }
+
+    fn load_error(name: &str, bytes: &[u8]) -> io::Error {
+        let path = temp_file(name);
+        fs::write(&path, bytes).unwrap();
+        let error = Checkpoint::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        error
+    }
+
+    #[test]
+    fn loads_what_was_saved() {
+        let path = temp_file("round-trip");
+        let saved = checkpoint();
+        saved.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.scene_hash, saved.scene_hash);
+        assert_eq!(loaded.seed, saved.seed);
+        assert_eq!(loaded.buffer.width, 3);
+        assert_eq!(loaded.buffer.height, 2);
+        assert_eq!(loaded.buffer.sums, saved.buffer.sums);
+        assert_eq!(loaded.buffer.sq_sums, saved.buffer.sq_sums);
+        assert_eq!(loaded.buffer.counts, saved.buffer.counts);
+
+        assert_eq!(&bytes[..8], MAGIC);
+        assert_eq!(bytes.len(), 8 + 8 + 8 + 4 + 4 + 6 * 20);
+        let mut partial = path.into_os_string();
+        partial.push(".partial");
+        assert!(!Path::new(&partial).exists());
+    }
+
+    #[test]
+    fn rejects_other_files_and_layouts() {
+        let path = temp_file("layout");
+        checkpoint().save(&path).unwrap();
+        let mut old = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The layout before the squared sums were stored
+        old[..8].copy_from_slice(b"rt2ckpt1");
+        for (name, bytes) in [
+            ("old", &old[..]),
+            ("image", b"P6\n3 2\n255\n\0\0\0".as_slice()),
+        ] {
+            let error = load_error(name, bytes);
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{name}");
+            assert_eq!(error.to_string(), "not a render checkpoint", "{name}");
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let path = temp_file("truncated");
+        checkpoint().save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // Cut in the magic, the header, and the middle and end of the samples
+        for len in [0, 5, 20, 31, 50, bytes.len() - 1] {
+            let error = load_error("truncated", &bytes[..len]);
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn scene_hash_is_fnv_1a() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = SceneHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+}
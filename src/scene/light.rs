@@ -0,0 +1,116 @@
+use crate::core::{Color, Point3, Vec3};
+
+/// Light that is not part of any geometry, so it cannot be hit by rays and
+/// only shows up through the surfaces it illuminates.
+#[derive(Debug, Clone)]
+pub enum Light {
+    /// Shines equally in all directions from a point, falling off with the square of distance
+    Point {
+        position: Point3,
+        color: Color,
+        intensity: f32,
+    },
+    /// Point light restricted to a cone around `direction`
+    Spot {
+        position: Point3,
+        direction: Vec3,
+        /// Half-angle of the cone, in degrees
+        angle: f32,
+        /// Fraction of the cone, measured from its edge, over which the light fades out
+        falloff: f32,
+        color: Color,
+        intensity: f32,
+    },
+    /// Parallel light arriving from infinitely far away, travelling along `direction`
+    Directional {
+        direction: Vec3,
+        color: Color,
+        intensity: f32,
+    },
+}
+
+/// Light arriving at a point from one light.
+pub struct Illumination {
+    /// Unit vector from the lit point towards the light
+    pub direction: Vec3,
+    /// Distance to the light, for limiting shadow rays
+    pub distance: f32,
+    pub radiance: Color,
+}
+
+impl Light {
+    /// Light this source delivers to `point`, ignoring occlusion.
+    /// Returns `None` when the point is outside the light's reach.
+    pub fn illuminate(&self, point: Point3) -> Option<Illumination> {
+        match *self {
+            Light::Point {
+                position,
+                color,
+                intensity,
+            } => {
+                let (direction, distance) = towards(point, position)?;
+                Some(Illumination {
+                    direction,
+                    distance,
+                    radiance: color * (intensity / (distance * distance)),
+                })
+            }
+            Light::Spot {
+                position,
+                direction: axis,
+                angle,
+                falloff,
+                color,
+                intensity,
+            } => {
+                let (direction, distance) = towards(point, position)?;
+
+                let cos_outer = angle.to_radians().cos();
+                let cos_inner = (angle * (1.0 - falloff.clamp(0.0, 1.0))).to_radians().cos();
+                let cos_theta = (-direction).dot(axis.normalize());
+                if cos_theta <= cos_outer {
+                    return None;
+                }
+                let cone = if cos_theta >= cos_inner {
+                    1.0
+                } else {
+                    smoothstep((cos_theta - cos_outer) / (cos_inner - cos_outer))
+                };
+
+                Some(Illumination {
+                    direction,
+                    distance,
+                    radiance: color * (intensity * cone / (distance * distance)),
+                })
+            }
+            Light::Directional {
+                direction,
+                color,
+                intensity,
+            } => {
+                let direction = -direction.normalize();
+                if direction.near_zero() {
+                    return None;
+                }
+                Some(Illumination {
+                    direction,
+                    distance: f32::INFINITY,
+                    radiance: color * intensity,
+                })
+            }
+        }
+    }
+}
+
+fn towards(point: Point3, position: Point3) -> Option<(Vec3, f32)> {
+    let to_light = position - point;
+    let distance = to_light.length();
+    if distance < 1e-6 {
+        return None;
+    }
+    Some((to_light / distance, distance))
+}
+
+fn smoothstep(x: f32) -> f32 {
+    x * x * (3.0 - 2.0 * x)
+}
@@ -1,6 +1,13 @@
 pub mod camera;
+pub mod checkpoint;
+pub mod light;
+#[allow(clippy::module_inception)]
 pub mod scene;
 pub mod storage;
+pub mod validation;
 
-pub use camera::Camera;
-pub use scene::Scene;
+pub use camera::{Camera, Projection};
+pub use checkpoint::{Checkpoint, SceneHasher};
+pub use light::Light;
+pub use scene::{Adaptive, Progressive, Scene};
+pub use validation::{SceneError, SceneProblem};
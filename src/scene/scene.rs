@@ -1,72 +1,134 @@
 use crate::core::*;
 use crate::pixels::*;
-use crate::random_float;
+use crate::{random_float, seed_rng};
 use crate::scene::*;
 use crate::scene::storage::*;
-use crate::objects::{Cube, Cylinder, Plane, Sphere};
+use crate::objects::{BvhNode, Transform};
 
-use indicatif::{ProgressBar, ProgressStyle};
 use std::fs;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use rayon::prelude::*;
 
 pub struct Scene {
-    objects: Vec<Box<dyn Hittable>>,
+    objects: Vec<Arc<dyn Hittable>>,
+    bvh: Option<BvhNode>,
+    emitters: Vec<Arc<dyn Hittable>>,
+    lights: Vec<Light>,
     background: Texture,
     camera: Camera,
     max_depth: u32,
+    roulette_depth: u32,
     sample_size: u32,
+    adaptive: Option<Adaptive>,
+    sampler: Sampler,
+    seed: Option<u64>,
+
+    // applied to the rendered image
+    tone_mapping: ToneMapping,
+    exposure: f32,
+    output: Option<String>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Scene {
     pub fn new() -> Self {
         Scene {
             objects: Vec::new(),
+            bvh: None,
+            emitters: Vec::new(),
+            lights: Vec::new(),
             background: Texture::SolidColor(Color::BLACK),
             camera: Camera::new(),
             max_depth: 1,
+            roulette_depth: 3,
             sample_size: 8,
+            adaptive: None,
+            sampler: Sampler::Independent,
+            seed: None,
+            tone_mapping: ToneMapping::None,
+            exposure: 0.0,
+            output: None,
         }
     }
 
-    pub fn load_from_file(path: &str) -> Result<Scene, Box<dyn std::error::Error>> {
+    /// Build a scene from a scene file. Files in an older format are upgraded
+    /// on the way; an invalid scene lists every problem found, not just the first.
+    /// Image and mesh paths are relative to the scene file's directory.
+    pub fn load_from_file(path: &str) -> Result<Scene, SceneError> {
         let data = fs::read_to_string(path)?;
-        let scene_data: SceneData = serde_json::from_str(&data)?;
+        let (scene_data, migration) = SceneData::from_json_migrated(&data)?;
+        if migration.upgraded() {
+            eprintln!(
+                "Warning: {path} is in scene format version {}, upgraded on load to version {SCENE_VERSION}",
+                migration.from_version
+            );
+            for change in &migration.changes {
+                eprintln!("  {change}");
+            }
+            eprintln!("  Use the `migrate` command to update the file");
+        }
 
+        let mut loader = SceneLoader::for_scene_file(path);
+        let problems = scene_data.validate(loader.base_dir());
+        if !problems.is_empty() {
+            return Err(SceneError::Invalid(problems));
+        }
+
+        // Every file exists, but images and meshes can still fail to load
+        let mut problems = Vec::new();
         let mut scene = Scene::new();
 
-        scene.set_background(scene_data.background.into());
+        match loader.texture(scene_data.background) {
+            Ok(background) => scene.set_background(background),
+            Err(e) => problems.push(SceneProblem::new("background", e)),
+        }
 
-        for object in scene_data.objects {
-            match object {
-                ObjectData::Sphere(s) => {
-                    let sphere: Sphere = s.into();
-                    scene.add_object(sphere);
-                }
-                ObjectData::Plane(p) => {
-                    let plane: Plane = p.into();
-                    scene.add_object(plane);
-                }
-                ObjectData::Cube(c) => {
-                    let cube: Cube = c.into();
-                    scene.add_object(cube);
-                }
-                ObjectData::Cylinder(cy) => {
-                    let cylinder: Cylinder = cy.into();
-                    scene.add_object(cylinder);
+        for (i, object) in scene_data.objects.into_iter().enumerate() {
+            let transform = object.transform_matrix();
+            let hittable = match loader.object(object) {
+                Ok(hittable) => hittable,
+                Err(e) => {
+                    problems.push(SceneProblem::new(format!("objects[{i}]"), e));
+                    continue;
                 }
+            };
+
+            match transform {
+                Some(matrix) => scene.add_object(Transform::new(hittable, matrix)),
+                None => scene.add_boxed_object(hittable),
             }
         }
 
-        let camera_data = scene_data.camera;
-        scene.camera_mut().set(
-            camera_data.position,
-            camera_data.look_at,
-            camera_data.up,
-            camera_data.fov,
-            camera_data.aspect_ratio,
-            camera_data.resolution,
-        );
+        for light in scene_data.lights {
+            scene.add_light(light.into());
+        }
+
+        scene.set_camera(scene_data.camera.into());
+
+        let render = scene_data.render;
+        scene.set_sample_size(render.samples.unwrap_or(RenderData::DEFAULT_SAMPLES));
+        scene.set_max_depth(render.max_depth.unwrap_or(RenderData::DEFAULT_MAX_DEPTH));
+        if let Some(roulette_depth) = render.roulette_depth {
+            scene.set_roulette_depth(roulette_depth);
+        }
+        if let Some(seed) = render.seed {
+            scene.set_seed(seed);
+        }
+        scene.set_sampler(render.sampler.into());
+        scene.set_tone_mapping(render.tone_mapping.into());
+        scene.set_exposure(render.exposure);
+        scene.set_output(render.output);
 
+        if !problems.is_empty() {
+            return Err(SceneError::Invalid(problems));
+        }
         Ok(scene)
     }
 
@@ -91,107 +153,537 @@ impl Scene {
         self.max_depth = depth;
     }
 
+    /// Most bounces a path takes.
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// Bounces every path takes before Russian roulette may end it. After that,
+    /// each bounce continues the path with a chance that falls with the light
+    /// it still carries, so dim paths stop early without darkening the image.
+    /// A depth of `max_depth` or more turns roulette off.
+    pub fn set_roulette_depth(&mut self, depth: u32) {
+        self.roulette_depth = depth;
+    }
+
+    pub fn roulette_depth(&self) -> u32 {
+        self.roulette_depth
+    }
+
     pub fn set_sample_size(&mut self, size: u32) {
         self.sample_size = size;
     }
 
+    /// Samples per pixel a render takes; the most any pixel takes when
+    /// sampling adaptively.
+    pub fn sample_size(&self) -> u32 {
+        self.sample_size
+    }
+
+    /// Sample only the pixels that are still noisy, up to the sample size,
+    /// instead of giving every pixel the same number of samples.
+    pub fn set_adaptive(&mut self, adaptive: Option<Adaptive>) {
+        self.adaptive = adaptive;
+    }
+
+    pub fn adaptive(&self) -> Option<Adaptive> {
+        self.adaptive
+    }
+
+    /// Where the random numbers of each sample come from.
+    pub fn set_sampler(&mut self, sampler: Sampler) {
+        self.sampler = sampler;
+    }
+
+    pub fn sampler(&self) -> Sampler {
+        self.sampler
+    }
+
+    /// Seed for the random numbers used while rendering. Every sample of every
+    /// pixel gets its own sequence derived from it, so a render is identical
+    /// however its rows are split across threads.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// The seed given to `set_seed` or by the scene file, if any; renders use 0 otherwise.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Curve applied to the rendered image, after the exposure.
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    /// Scale the rendered image by `2^exposure`, so each stop doubles the light.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure;
+    }
+
+    /// File the render is meant to be saved to, if the scene names one.
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+
+    pub fn set_output(&mut self, output: Option<String>) {
+        self.output = output;
+    }
+
     pub fn add_object<T: Hittable + 'static>(&mut self, object: T) {
-        self.objects.push(Box::new(object));
+        self.add_shared_object(Arc::new(object));
     }
 
     pub fn add_boxed_object(&mut self, object: Box<dyn Hittable>) {
+        self.add_shared_object(Arc::from(object));
+    }
+
+    fn add_shared_object(&mut self, object: Arc<dyn Hittable>) {
+        if object.is_emissive() {
+            self.emitters.push(object.clone());
+        }
         self.objects.push(object);
+        self.bvh = None;
     }
 
-    pub fn render(&mut self, path: &str, parallelized: bool) -> std::io::Result<()> {
-        let (width, height) = self.camera().resolution();
-        let mut image = Image::new(width as usize, height as usize);
-
-        // Create progress bar
-        let prog_bar = ProgressBar::new(height as u64);
-        prog_bar.set_style(
-            ProgressStyle::with_template(
-                "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos:>3}/{len:3} lines ({percent}%) {eta}"
-            )
-            .unwrap()
-            .progress_chars("█▉▊▋▌▍▎▏  ")
-        );
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
 
-        println!("Starting render: {width}x{height} pixels");
-        if parallelized {
-            println!("Using parallelized rendering");
+    /// Build the bounding volume hierarchy used by `ray_color`.
+    /// Rendering builds it when needed; adding objects invalidates it.
+    pub fn build_bvh(&mut self) {
+        self.bvh = if self.objects.is_empty() {
+            None
         } else {
-            println!("Using single-threaded rendering");
+            Some(BvhNode::new(self.objects.clone()))
+        };
+    }
+
+    /// Trace every pixel of the camera's image and return the result, with the
+    /// scene's exposure and tone mapping applied.
+    ///
+    /// `progress`, if given, is called after each finished row with the number
+    /// of rows done so far and the total; rows may finish out of order when
+    /// `parallelized` is set. When sampling adaptively it is instead called
+    /// after each pass with the number of pixels done and the total.
+    pub fn render(
+        &mut self,
+        parallelized: bool,
+        progress: Option<&(dyn Fn(u32, u32) + Sync)>,
+    ) -> Image {
+        let (width, height) = self.camera().resolution();
+        let mut buffer = SampleBuffer::new(width as usize, height as usize);
+        self.render_into(&mut buffer, parallelized, progress)
+    }
+
+    /// `render`, keeping the samples in `buffer`, which must be empty and the
+    /// size of the camera's image.
+    pub fn render_into(
+        &mut self,
+        buffer: &mut SampleBuffer,
+        parallelized: bool,
+        progress: Option<&(dyn Fn(u32, u32) + Sync)>,
+    ) -> Image {
+        // Pixels converge at different times, so adaptive renders go in passes
+        if self.adaptive.is_some() {
+            let progress = progress.map(|progress| progress as &dyn Fn(u32, u32));
+            return self.render_progressive(
+                buffer,
+                &Progressive::default(),
+                parallelized,
+                progress,
+                &mut |_, _| {},
+            );
+        }
+        self.add_samples(buffer, self.sample_size, parallelized, progress);
+        self.develop(buffer)
+    }
+
+    /// Take `samples` more samples for every pixel of `buffer`, which must be
+    /// the size of the camera's image. Each pixel continues its own sequence of
+    /// samples, so adding them over several calls gives the same image as
+    /// taking them all at once. `progress` works as in `render`.
+    pub fn add_samples(
+        &mut self,
+        buffer: &mut SampleBuffer,
+        samples: u32,
+        parallelized: bool,
+        progress: Option<&(dyn Fn(u32, u32) + Sync)>,
+    ) {
+        let plan = vec![samples; buffer.counts.len()];
+        self.take_samples(buffer, &plan, parallelized, progress);
+    }
+
+    /// Take `plan[i]` more samples for pixel `i` of `buffer`.
+    fn take_samples(
+        &mut self,
+        buffer: &mut SampleBuffer,
+        plan: &[u32],
+        parallelized: bool,
+        progress: Option<&(dyn Fn(u32, u32) + Sync)>,
+    ) {
+        if self.bvh.is_none() {
+            self.build_bvh();
         }
 
+        let (width, height) = (buffer.width(), buffer.height());
+        let seed = self.seed.unwrap_or(0);
+        let rows_done = AtomicU32::new(0);
+        let scene = &*self;
+
         // Common rendering logic for each row
-        let render_row = |y: u32| {
-            let mut row_pixels = Vec::with_capacity(width as usize);
+        type Row<'r> = (usize, ((&'r mut [Color], &'r mut [f32]), &'r mut [u32]));
+        let render_row = |(y, ((sums, sq_sums), counts)): Row| {
             for x in 0..width {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..self.sample_size {
+                let pixel = (y * width + x) as u64;
+                let first = counts[x];
+                let samples = plan[y * width + x];
+                let pixel_seed = sample_seed(seed, pixel, u32::MAX);
+                for sample in first..first + samples {
+                    seed_rng(sample_seed(seed, pixel, sample));
+                    scene
+                        .sampler
+                        .begin_sample(pixel_seed, sample, scene.sample_size);
                     let horizontal_offset = (x as f32 + random_float()) / width as f32;
                     let vertical_offset = 1.0 - ((y as f32 + random_float()) / height as f32);
-                    let ray = self
-                        .camera()
-                        .generate_ray(horizontal_offset, vertical_offset);
-                    pixel_color = pixel_color + self.ray_color(&ray, self.max_depth);
+                    let camera = scene.camera();
+                    let color = if camera.in_view(horizontal_offset, vertical_offset) {
+                        let ray = camera.generate_ray(horizontal_offset, vertical_offset);
+                        scene.ray_color(&ray, scene.max_depth)
+                    } else {
+                        Color::BLACK
+                    };
+                    sums[x] = sums[x] + color;
+                    sq_sums[x] += color.luminance() * color.luminance();
                 }
-                let color = pixel_color / self.sample_size as i32;
-                row_pixels.push(color);
+                counts[x] = first + samples;
+            }
+            sampler::end_sample();
+            if let Some(progress) = progress {
+                progress(rows_done.fetch_add(1, Ordering::Relaxed) + 1, height as u32);
             }
-            prog_bar.inc(1);
-            (y, row_pixels)
         };
 
-        let rows: Vec<(u32, Vec<Color>)> = if parallelized {
-            (0..height).into_par_iter().map(render_row).collect()
+        if parallelized {
+            buffer
+                .sums
+                .par_chunks_mut(width)
+                .zip(buffer.sq_sums.par_chunks_mut(width))
+                .zip(buffer.counts.par_chunks_mut(width))
+                .enumerate()
+                .for_each(render_row);
         } else {
-            (0..height).map(render_row).collect()
+            buffer
+                .sums
+                .chunks_mut(width)
+                .zip(buffer.sq_sums.chunks_mut(width))
+                .zip(buffer.counts.chunks_mut(width))
+                .enumerate()
+                .for_each(render_row);
+        }
+    }
+
+    /// Render in passes over the whole image, adding `settings.pass_samples`
+    /// samples per pixel each time, until every pixel of `buffer` has the
+    /// scene's sample size or the time limit runs out. Samples already in
+    /// `buffer` count, so an interrupted render can be continued. When
+    /// sampling adaptively, passes skip the pixels that have converged.
+    ///
+    /// `checkpoint` is handed the image so far, along with the samples behind
+    /// it, as often as `settings` asks for. `progress`, if given, is called
+    /// after each pass with the fewest samples any pixel has and the target,
+    /// or when sampling adaptively, the number of pixels done and the total.
+    pub fn render_progressive(
+        &mut self,
+        buffer: &mut SampleBuffer,
+        settings: &Progressive,
+        parallelized: bool,
+        progress: Option<&dyn Fn(u32, u32)>,
+        checkpoint: &mut dyn FnMut(&Image, &SampleBuffer),
+    ) -> Image {
+        let start = Instant::now();
+        let mut last_checkpoint = start;
+        let mut passes_since_checkpoint = 0;
+
+        let pass_samples = settings.pass_samples.max(1);
+        let mut errors = vec![None; buffer.counts.len()];
+        let mut plan = self.pass_plan(buffer, pass_samples, &mut errors);
+
+        while plan.iter().any(|&samples| samples > 0) {
+            self.take_samples(buffer, &plan, parallelized, None);
+            plan = self.pass_plan(buffer, pass_samples, &mut errors);
+            if let Some(progress) = progress {
+                match self.adaptive {
+                    Some(_) => {
+                        let done = plan.iter().filter(|&&samples| samples == 0).count();
+                        progress(done as u32, plan.len() as u32)
+                    }
+                    None => progress(buffer.min_samples(), self.sample_size),
+                }
+            }
+
+            let out_of_time = settings
+                .time_limit
+                .is_some_and(|limit| start.elapsed() >= limit);
+            if out_of_time || plan.iter().all(|&samples| samples == 0) {
+                break;
+            }
+
+            passes_since_checkpoint += 1;
+            let due = settings
+                .checkpoint_passes
+                .is_some_and(|passes| passes_since_checkpoint >= passes)
+                || settings
+                    .checkpoint_interval
+                    .is_some_and(|interval| last_checkpoint.elapsed() >= interval);
+            if due {
+                checkpoint(&self.develop(buffer), buffer);
+                last_checkpoint = Instant::now();
+                passes_since_checkpoint = 0;
+            }
+        }
+
+        self.develop(buffer)
+    }
+
+    /// Samples each pixel of `buffer` takes in the next pass: `pass_samples`,
+    /// up to the sample size. Adaptively, every pixel first gets the minimum
+    /// in one go, then keeps going while it or a neighbour is noisier than the
+    /// threshold; looking at the neighbours keeps isolated pixels that happen
+    /// to have converged early from leaving holes in a noisy region.
+    ///
+    /// Noisy pixels double their samples each pass, up to
+    /// `MAX_ADAPTIVE_PASS` at a time, so the image is judged a few times
+    /// rather than after every sample. `errors` holds each pixel's error along
+    /// with its sample count when it was estimated, and pixels that were not
+    /// sampled since keep theirs. The plan only depends on `buffer`, so a
+    /// render continued from its samples ends up the same.
+    fn pass_plan(
+        &self,
+        buffer: &SampleBuffer,
+        pass_samples: u32,
+        errors: &mut [Option<(u32, f32)>],
+    ) -> Vec<u32> {
+        let Some(adaptive) = self.adaptive else {
+            return buffer
+                .counts
+                .iter()
+                .map(|&count| self.sample_size.saturating_sub(count).min(pass_samples))
+                .collect();
         };
 
-        prog_bar.finish();
+        let (width, height) = (buffer.width(), buffer.height());
+        let noisy: Vec<bool> = errors
+            .iter_mut()
+            .enumerate()
+            .map(|(i, judged)| {
+                let count = buffer.counts[i];
+                let error = match *judged {
+                    Some((judged_at, error)) if judged_at == count => error,
+                    _ => {
+                        let error = buffer.pixel_error(i);
+                        *judged = Some((count, error));
+                        error
+                    }
+                };
+                error > adaptive.threshold
+            })
+            .collect();
+        let min_samples = adaptive.min_samples.min(self.sample_size);
+        let max_pass = MAX_ADAPTIVE_PASS.max(pass_samples);
+
+        (0..buffer.counts.len())
+            .map(|i| {
+                let count = buffer.counts[i];
+                let (x, y) = (i % width, i / width);
+                if count < min_samples {
+                    // Noise can't be judged before the minimum
+                    return min_samples - count;
+                }
+                let noisy_around = (y.saturating_sub(1)..(y + 2).min(height)).any(|ny| {
+                    (x.saturating_sub(1)..(x + 2).min(width)).any(|nx| noisy[ny * width + nx])
+                });
+                if !noisy_around {
+                    return 0;
+                }
+                let pass = count.clamp(pass_samples, max_pass);
+                self.sample_size.saturating_sub(count).min(pass)
+            })
+            .collect()
+    }
 
-        println!("Saving to: {path}");
-        for (y, row) in rows {
-            for (x, color) in row.into_iter().enumerate() {
-                image.set_pixel(x, y as usize, color);
+    /// Average the samples in `buffer` into an image, with the scene's exposure
+    /// and tone mapping applied.
+    pub fn develop(&self, buffer: &SampleBuffer) -> Image {
+        let mut image = buffer.to_image();
+        image.tone_map(self.tone_mapping, self.exposure);
+        image
+    }
+
+    /// Find the nearest intersection, through the BVH when it has been built.
+    fn closest_hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        if let Some(bvh) = &self.bvh {
+            return bvh.hit(ray, t_min, t_max);
+        }
+
+        let mut closest_so_far = t_max;
+        let mut final_hit = None;
+
+        for object in &self.objects {
+            if let Some(hit) = object.hit(ray, t_min, closest_so_far) {
+                closest_so_far = hit.t;
+                final_hit = Some(hit);
             }
         }
 
-        image.save_ppm(path)?;
-        Ok(())
+        final_hit
     }
 
+    /// Estimate light arriving at a hit directly from one randomly chosen
+    /// emissive object, by tracing a shadow ray to a point on it.
+    ///
+    /// The sample is weighted by the material's response to light from that
+    /// direction, so it replaces the light that bounces that are not specular
+    /// pick up by chance and keeps the same average.
+    fn sample_emitters(&self, ray: &Ray, hit: &HitRecord) -> Color {
+        if self.emitters.is_empty() {
+            return Color::BLACK;
+        }
+
+        let index = ((random_float() * self.emitters.len() as f32) as usize).min(self.emitters.len() - 1);
+        let origin = hit.p + hit.normal * 1e-4;
+        let Some(sample) = self.emitters[index].sample_surface(origin) else {
+            return Color::BLACK;
+        };
+
+        let to_light = sample.p - origin;
+        let distance = to_light.length();
+        let direction = to_light / distance;
+
+        let weight = hit.material.evaluate(ray, hit, direction);
+        let cos_light = sample.normal.dot(-direction).abs();
+        if weight == Color::BLACK || cos_light < 1e-6 || sample.pdf <= 0.0 {
+            return Color::BLACK;
+        }
+
+        // Anything between the hit and the sampled point blocks the light
+        let shadow_ray = Ray::new(origin, direction);
+        if self.closest_hit(&shadow_ray, 1e-6, distance * (1.0 - 1e-3)).is_some() {
+            return Color::BLACK;
+        }
+
+        // Convert the area density to a solid-angle density, including the light choice
+        let light_pdf = sample.pdf * distance * distance / cos_light / self.emitters.len() as f32;
+        sample.emission * weight * (1.0 / light_pdf)
+    }
+
+    /// Light arriving at a hit from every analytic light that can see it.
+    ///
+    /// Weighted like `sample_emitters`, since a bounce can never find these
+    /// lights on its own.
+    fn direct_lighting(&self, ray: &Ray, hit: &HitRecord) -> Color {
+        let origin = hit.p + hit.normal * 1e-4;
+        let mut total = Color::BLACK;
+
+        for light in &self.lights {
+            let Some(illumination) = light.illuminate(origin) else {
+                continue;
+            };
+
+            let weight = hit.material.evaluate(ray, hit, illumination.direction);
+            if weight == Color::BLACK {
+                continue;
+            }
+
+            let shadow_ray = Ray::new(origin, illumination.direction);
+            if self.closest_hit(&shadow_ray, 1e-6, illumination.distance).is_some() {
+                continue;
+            }
+
+            total = total + illumination.radiance * weight;
+        }
+
+        total
+    }
+
+    /// Light arriving along `ray`, a ray from the camera, within the camera's
+    /// clip range.
     pub fn ray_color(
         &self,
         ray: &Ray,
         depth: u32,
+    ) -> Color {
+        self.trace(ray, depth, true, 0, Color::WHITE)
+    }
+
+    /// `count_emission` is false after a bounce that is not specular, whose
+    /// light from emissive objects was already gathered by `sample_emitters`. `bounces`
+    /// is the number of bounces the path has taken to get here, and
+    /// `throughput` the product of their attenuations.
+    fn trace(
+        &self,
+        ray: &Ray,
+        depth: u32,
+        count_emission: bool,
+        bounces: u32,
+        throughput: Color,
     ) -> Color {
         if depth == 0 {
             return Color::BLACK;
         }
 
-        let mut closest_so_far = 50.0;
-        let mut final_hit = None;
+        // Only what the camera sees directly is clipped
+        let (t_min, t_max) = if bounces == 0 {
+            let (near, far) = self.camera.clip_range(ray);
+            (near.max(1e-6), far)
+        } else {
+            (1e-6, f32::INFINITY)
+        };
 
-        for object in &self.objects {
-            if let Some(hit) = object.hit(ray, 1e-6, closest_so_far) {
-                closest_so_far = hit.t;
-                final_hit = Some(hit);
+        if let Some(hit) = self.closest_hit(ray, t_min, t_max) {
+            let glow = match hit.material.emission() {
+                Some(emission) if count_emission => emission,
+                _ => Color::BLACK,
+            };
+            let mut final_color = glow + self.direct_lighting(ray, &hit);
+
+            // The light sample stands in for a bounce that is not specular
+            // hitting a light, so it needs a bounce left
+            let sample_emitters = !self.emitters.is_empty() && depth > 1;
+            if sample_emitters {
+                final_color = final_color + self.sample_emitters(ray, &hit);
             }
-        }
 
-        if let Some(hit) = final_hit {
-            let glow = hit.material.emission.unwrap_or(Color::BLACK);
-            let mut final_color = glow;
+            if let Some(scatter) = hit.material.sample(ray, &hit) {
+
+                // Russian roulette: end the path with a chance that grows as its
+                // throughput falls, and weight the paths that go on by how
+                // rarely they do, so the average stays the same
+                let mut attenuation = scatter.attenuation;
+                let mut throughput = throughput * attenuation;
+                let survives = if bounces + 1 > self.roulette_depth && depth > 1 {
+                    let survival = throughput.max_component().min(0.95);
+                    let survives = random_float() < survival;
+                    if survives {
+                        attenuation = attenuation * (1.0 / survival);
+                        throughput = throughput * (1.0 / survival);
+                    }
+                    survives
+                } else {
+                    true
+                };
 
-            if let Some(scatter) = hit.material.scatter(ray, &hit) {
-                let bounced = self.ray_color(
-                    &scatter.scattered_ray,
-                    depth - 1,
-                );
-                final_color = final_color + scatter.attenuation * bounced;
+                if survives {
+                    let incoming = self.trace(
+                        &scatter.scattered_ray,
+                        depth - 1,
+                        scatter.is_specular || !sample_emitters,
+                        bounces + 1,
+                        throughput,
+                    );
+                    final_color = final_color + attenuation * incoming;
+                }
             }
 
             return final_color;
@@ -207,3 +699,331 @@ impl Scene {
         }
     }
 }
+
+/// Most samples a noisy pixel takes in one adaptive pass, unless the passes
+/// are set to be larger.
+const MAX_ADAPTIVE_PASS: u32 = 64;
+
+/// How `Scene::render_progressive` splits a render into passes, and when it
+/// stops early or hands out the image so far.
+#[derive(Debug, Clone)]
+pub struct Progressive {
+    /// Samples per pixel added by each pass over the image. Adaptive passes
+    /// take more once pixels have more samples.
+    pub pass_samples: u32,
+    /// Stop after the first pass that ends past this much time
+    pub time_limit: Option<Duration>,
+    /// Hand out the image every this many passes
+    pub checkpoint_passes: Option<u32>,
+    /// Hand out the image once this much time has gone by since the last time
+    pub checkpoint_interval: Option<Duration>,
+}
+
+impl Default for Progressive {
+    fn default() -> Self {
+        Self {
+            pass_samples: 1,
+            time_limit: None,
+            checkpoint_passes: None,
+            checkpoint_interval: None,
+        }
+    }
+}
+
+/// When adaptive sampling stops taking samples for a pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adaptive {
+    /// Samples every pixel takes before its noise is judged
+    pub min_samples: u32,
+    /// A pixel is done once its estimated error, on the 0 to 1 scale of the
+    /// displayed image, is below this for it and its neighbours
+    pub threshold: f32,
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self {
+            min_samples: 16,
+            threshold: 0.01,
+        }
+    }
+}
+
+/// Mix the scene seed, pixel index and sample index into one well-spread seed.
+fn sample_seed(seed: u64, pixel: u64, sample: u32) -> u64 {
+    let mut z = seed ^ pixel.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ ((sample as u64) << 40);
+    // SplitMix64 finalizer
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Material, PbrMaterial};
+    use crate::objects::{Plane, Sphere};
+    use std::f32::consts::PI;
+
+    /// Diffuse, glass, metal and PBR surfaces lit by an emissive sphere, a
+    /// point light and the sky, so paths take every kind of bounce and both
+    /// kinds of light sampling.
+    fn test_scene(scene: &mut Scene) {
+        let material = |color: Color, diffuse, reflectivity, transparency| Material {
+            texture: Texture::SolidColor(color),
+            diffuse,
+            reflectivity,
+            transparency,
+            index_of_refraction: 1.5,
+            emission: None,
+        };
+
+        scene.set_background(Texture::Gradient(Color::LIGHT_BLUE, Color::WHITE, PI));
+        scene.camera_mut().set(
+            Point3::new(0.0, 1.5, 5.0),
+            Point3::new(0.0, 0.5, 0.0),
+            Vec3::Y,
+            50.0,
+            1.0,
+            (24, 16),
+        );
+
+        scene.add_object(Plane::new(
+            Point3::ZERO,
+            Vec3::new(8.0, 0.0, 8.0),
+            Material {
+                texture: Texture::Checkerboard(Color::WHITE, Color::GRAY, 4.0),
+                ..material(Color::WHITE, 1.0, 0.0, 0.0)
+            },
+        ));
+        scene.add_object(Sphere::new(
+            Point3::new(-1.2, 0.5, 0.0),
+            0.5,
+            material(Color::WHITE, 0.0, 0.0, 1.0),
+        ));
+        scene.add_object(Sphere::new(
+            Point3::new(0.0, 0.5, -0.5),
+            0.5,
+            material(Color::PASTEL_LIME, 0.2, 0.8, 0.0),
+        ));
+        scene.add_object(Sphere::new(
+            Point3::new(1.2, 0.5, 0.0),
+            0.5,
+            PbrMaterial {
+                base_color: Texture::SolidColor(Color::new(0.9, 0.6, 0.2)),
+                metallic: 1.0,
+                roughness: 0.3,
+                ..PbrMaterial::default()
+            },
+        ));
+        scene.add_object(Sphere::new(
+            Point3::new(0.0, 3.0, 1.0),
+            0.4,
+            Material {
+                emission: Some(Color::WHITE * 10.0),
+                ..material(Color::WHITE, 0.0, 0.0, 0.0)
+            },
+        ));
+        scene.add_light(Light::Point {
+            position: Point3::new(2.0, 2.5, 2.0),
+            color: Color::WHITE,
+            intensity: 5.0,
+        });
+    }
+
+    fn small_scene(sampler: Sampler) -> Scene {
+        let mut scene = Scene::new();
+        test_scene(&mut scene);
+        scene.set_sample_size(6);
+        scene.set_max_depth(6);
+        scene.set_sampler(sampler);
+        scene.set_seed(7);
+        scene
+    }
+
+    fn progressive(scene: &mut Scene, pass_samples: u32, parallelized: bool) -> Image {
+        let (width, height) = scene.camera().resolution();
+        let mut buffer = SampleBuffer::new(width as usize, height as usize);
+        let settings = Progressive {
+            pass_samples,
+            ..Progressive::default()
+        };
+        scene.render_progressive(&mut buffer, &settings, parallelized, None, &mut |_, _| {})
+    }
+
+    #[test]
+    fn seeded_renders_are_identical_however_they_are_taken() {
+        for sampler in [Sampler::Independent, Sampler::Stratified, Sampler::Sobol] {
+            let mut scene = small_scene(sampler);
+            let one_shot = scene.render(false, None);
+
+            assert!(
+                one_shot.pixels == scene.render(true, None).pixels,
+                "{sampler:?}: parallel"
+            );
+            assert!(
+                one_shot.pixels == progressive(&mut scene, 1, false).pixels,
+                "{sampler:?}: progressive, one sample per pass"
+            );
+            assert!(
+                one_shot.pixels == progressive(&mut scene, 4, true).pixels,
+                "{sampler:?}: progressive, parallel, uneven passes"
+            );
+
+            let (width, height) = scene.camera().resolution();
+            let mut buffer = SampleBuffer::new(width as usize, height as usize);
+            scene.add_samples(&mut buffer, 2, true, None);
+            scene.add_samples(&mut buffer, 4, false, None);
+            assert!(
+                one_shot.pixels == scene.develop(&buffer).pixels,
+                "{sampler:?}: added"
+            );
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_renders() {
+        let mut scene = small_scene(Sampler::Independent);
+        let first = scene.render(true, None);
+        scene.set_seed(8);
+        assert!(first.pixels != scene.render(true, None).pixels);
+    }
+
+    #[test]
+    fn adaptive_renders_take_few_passes_and_resume_exactly() {
+        let mut scene = small_scene(Sampler::Independent);
+        scene.set_sample_size(256);
+        scene.set_adaptive(Some(Adaptive {
+            min_samples: 8,
+            threshold: 0.005,
+        }));
+        let settings = Progressive {
+            checkpoint_passes: Some(1),
+            ..Progressive::default()
+        };
+        let (width, height) = scene.camera().resolution();
+
+        let passes = std::cell::Cell::new(0);
+        let mut saved = Vec::new();
+        let mut buffer = SampleBuffer::new(width as usize, height as usize);
+        let image = scene.render_progressive(
+            &mut buffer,
+            &settings,
+            true,
+            Some(&|_, _| passes.set(passes.get() + 1)),
+            &mut |_, buffer| saved.push(buffer.clone()),
+        );
+
+        // A pixel goes through 8, 16, 32, 64, 128, 192 and 256 samples, and
+        // pixels that a noisy neighbour wakes up again add a few more passes
+        assert!(passes.get() < 20, "{} passes", passes.get());
+        let (fewest, most) = (buffer.min_samples(), *buffer.counts.iter().max().unwrap());
+        assert!(
+            fewest >= 8 && fewest < most && most == 256,
+            "{fewest} to {most} samples"
+        );
+
+        for mut resumed in saved {
+            let continued =
+                scene.render_progressive(&mut resumed, &settings, false, None, &mut |_, _| {});
+            assert!(resumed.counts == buffer.counts);
+            assert!(continued.pixels == image.pixels);
+        }
+    }
+
+    #[test]
+    fn wide_fisheye_corners_are_black() {
+        let mut scene = Scene::new();
+        scene.set_background(Texture::SolidColor(Color::WHITE));
+        // All of each corner pixel is more than 190° off the view direction
+        scene.camera_mut().set_resolution((16, 12));
+        scene
+            .camera_mut()
+            .set_projection(Projection::Fisheye { fov: 360.0 });
+        scene.set_sample_size(4);
+
+        let image = scene.render(false, None);
+        for (x, y) in [(0, 0), (15, 0), (0, 11), (15, 11)] {
+            assert_eq!(image.pixels[y * 16 + x], Color::BLACK, "corner {x}, {y}");
+        }
+        assert_eq!(image.pixels[6 * 16 + 8], Color::WHITE);
+    }
+
+    #[test]
+    fn scene_files_default_to_more_samples_than_new_scenes() {
+        let scene = Scene::new();
+        assert_eq!((scene.sample_size(), scene.max_depth()), (8, 1));
+
+        let path = std::env::temp_dir().join(format!("rt_2-defaults-{}.json", std::process::id()));
+        fs::write(&path, serde_json::to_string(&SceneData::default()).unwrap()).unwrap();
+        let loaded = Scene::load_from_file(path.to_str().unwrap());
+        let _ = fs::remove_file(&path);
+        let scene = loaded.unwrap();
+        assert_eq!(
+            (scene.sample_size(), scene.max_depth()),
+            (RenderData::DEFAULT_SAMPLES, RenderData::DEFAULT_MAX_DEPTH)
+        );
+    }
+
+    fn classic(diffuse: f32, emission: Option<Color>) -> Material {
+        Material {
+            texture: Texture::SolidColor(Color::WHITE),
+            diffuse,
+            reflectivity: 0.0,
+            transparency: 0.0,
+            index_of_refraction: 1.0,
+            emission,
+        }
+    }
+
+    /// A white diffuse sphere in surroundings that are equally bright in every
+    /// direction reflects all the light it gets, so it disappears into them.
+    #[test]
+    fn white_furnace() {
+        for light_sampling in [false, true] {
+            let mut scene = Scene::new();
+            scene.set_background(Texture::SolidColor(Color::WHITE));
+            scene.add_object(Sphere::new(Point3::ZERO, 1.0, classic(1.0, None)));
+            if light_sampling {
+                // As bright as the background, so the surroundings stay uniform,
+                // but diffuse bounces now sample it directly
+                scene.add_object(Sphere::new(
+                    Point3::new(0.0, 2.5, 0.0),
+                    0.8,
+                    classic(0.0, Some(Color::WHITE)),
+                ));
+            }
+            scene.camera_mut().set(
+                Point3::new(0.0, 0.0, 4.0),
+                Point3::ZERO,
+                Vec3::Y,
+                20.0,
+                1.0,
+                (6, 6),
+            );
+            scene.set_sample_size(512);
+            scene.set_max_depth(16);
+            scene.set_seed(23);
+
+            let image = scene.render(true, None);
+            for pixel in &image.pixels {
+                for value in [pixel.r, pixel.g, pixel.b] {
+                    assert!(
+                        (value - 1.0).abs() < 0.03,
+                        "light sampling {light_sampling}: pixel {value}"
+                    );
+                }
+            }
+            let mean = image
+                .pixels
+                .iter()
+                .map(|pixel| pixel.luminance())
+                .sum::<f32>()
+                / image.pixels.len() as f32;
+            assert!(
+                (mean - 1.0).abs() < 0.005,
+                "light sampling {light_sampling}: mean {mean}"
+            );
+        }
+    }
+}
@@ -1,37 +1,206 @@
-use serde::{Deserialize, Serialize};
+use glam::{Affine3A, Quat};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use crate::core::color::Color;
+use crate::core::Hittable;
 use crate::core::vec3::{Point3, Vec3};
 use crate::pixels::texture::Texture;
-use crate::objects::{Sphere, Plane, Cube, Cylinder};
+use crate::pixels::tone_mapping::ToneMapping;
+use crate::core::sampler::Sampler;
+use crate::objects::{Sphere, Plane, Cube, Cylinder, Mesh};
 use crate::pixels::image::Image;
+use crate::scene::camera::{Camera, Projection};
+use crate::scene::light::Light;
+use crate::material::{Material, PbrMaterial, Scatter};
+
+/// Version of the scene format written by this build. Older documents are
+/// upgraded by `SceneData::from_json` when they are read.
+pub const SCENE_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SceneData {
+    pub version: u32,
     pub objects: Vec<ObjectData>,
     pub camera: CameraData,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     pub background: TextureData,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightData>,
+    #[serde(default, skip_serializing_if = "RenderData::is_empty")]
+    pub render: RenderData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraData {
+    pub position: Point3,
+    pub look_at: Point3,
+    pub up: Vec3,
+    /// Vertical field of view, in degrees
+    pub fov: f32,
+    /// Distance from the camera to the image plane. The image plane is sized
+    /// by `fov`, so this has no effect on the image; it only keeps the value
+    /// that version 0 files stored in `aspect_ratio`.
+    #[serde(default = "default_focal_length")]
+    pub focal_length: f32,
+    /// Width over height of the view; follows `resolution` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f32>,
+    pub resolution: (u32, u32),
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub projection: ProjectionData,
+    /// Lens diameter; 0 keeps everything in focus
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub aperture: f32,
+    /// Distance to the plane in focus; defaults to the distance to `look_at`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_distance: Option<f32>,
+    /// Number of aperture blades; fewer than 3 gives a round aperture
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub blades: u32,
+    /// Nothing nearer to the camera than this is seen
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub near: f32,
+    /// Nothing farther from the camera than this is seen; unbounded when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub far: Option<f32>,
 }
 
 impl Default for SceneData {
     fn default() -> Self {
         Self {
+            version: SCENE_VERSION,
             objects: Vec::new(),
-            camera: Default::default(),
+            camera: CameraData::default(),
             background: TextureData::default(),
+            lights: Vec::new(),
+            render: RenderData::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CameraData {
-    pub position: Point3,
-    pub look_at: Point3,
-    pub up: Vec3,
-    pub fov: f32,
-    pub aspect_ratio: f32,
-    pub resolution: (u32, u32),
+impl SceneData {
+    /// Parse a scene document, upgrading it first if it was written in an
+    /// older version of the format.
+    pub fn from_json(json: &str) -> serde_json::Result<SceneData> {
+        Self::from_json_migrated(json).map(|(data, _)| data)
+    }
+
+    /// Like `from_json`, also returning what was changed to upgrade the document.
+    pub fn from_json_migrated(json: &str) -> serde_json::Result<(SceneData, MigrationReport)> {
+        let mut value: Value = serde_json::from_str(json)?;
+        let report = migrate(&mut value)?;
+        Ok((serde_json::from_value(value)?, report))
+    }
+
+    /// Paths of every file the scene refers to: image textures and meshes.
+    pub fn file_paths_mut(&mut self) -> Vec<&mut String> {
+        let mut paths = Vec::new();
+        if let TextureData::Image(path) = &mut self.background {
+            paths.push(path);
+        }
+        for object in &mut self.objects {
+            let material = match object {
+                ObjectData::Sphere(s) => &mut s.material,
+                ObjectData::Plane(p) => &mut p.material,
+                ObjectData::Cube(c) => &mut c.material,
+                ObjectData::Cylinder(cy) => &mut cy.material,
+                ObjectData::Mesh(m) => {
+                    paths.push(&mut m.path);
+                    &mut m.material
+                }
+            };
+            if let TextureData::Image(path) = material.texture_mut() {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+}
+
+/// What `migrate` did to bring a scene document up to `SCENE_VERSION`.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationReport {
+    /// Version the document was written in
+    pub from_version: u32,
+    /// Description of each change made, in the order they were applied
+    pub changes: Vec<String>,
+}
+
+impl MigrationReport {
+    /// Whether the document was in an older version of the format.
+    pub fn upgraded(&self) -> bool {
+        self.from_version < SCENE_VERSION
+    }
+}
+
+/// Upgrades a scene document by one version, returning a description of
+/// each change it made.
+type Migration = fn(&mut Map<String, Value>) -> Vec<String>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+/// Bumping `SCENE_VERSION` requires adding the step that gets there.
+const MIGRATIONS: [Migration; SCENE_VERSION as usize] = [migrate_camera_focal_length];
+
+/// Bring a scene document up to `SCENE_VERSION` one version at a time.
+/// Documents without a `version` field predate versioning and count as version 0.
+pub fn migrate(value: &mut Value) -> serde_json::Result<MigrationReport> {
+    let Some(scene) = value.as_object_mut() else {
+        return Err(de::Error::custom("a scene must be a JSON object"));
+    };
+    let from_version = match scene.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| de::Error::custom(format!("invalid scene version {version}")))?,
+    };
+    if from_version > SCENE_VERSION {
+        return Err(de::Error::custom(format!(
+            "scene version {from_version} is newer than the latest supported version {SCENE_VERSION}"
+        )));
+    }
+
+    let mut changes = Vec::new();
+    for (version, step) in MIGRATIONS.iter().enumerate().skip(from_version as usize) {
+        for change in step(scene) {
+            changes.push(format!("{} -> {}: {change}", version, version + 1));
+        }
+    }
+    scene.insert("version".to_string(), SCENE_VERSION.into());
+
+    Ok(MigrationReport {
+        from_version,
+        changes,
+    })
+}
+
+/// Version 0 passed the camera's `aspect_ratio` on as its focal length and
+/// took the real aspect ratio from the resolution.
+fn migrate_camera_focal_length(scene: &mut Map<String, Value>) -> Vec<String> {
+    let Some(camera) = scene.get_mut("camera").and_then(|c| c.as_object_mut()) else {
+        return Vec::new();
+    };
+    let Some(focal_length) = camera.remove("aspect_ratio") else {
+        return Vec::new();
+    };
+    let change = format!("camera `aspect_ratio` {focal_length} is now `focal_length`");
+    camera.insert("focal_length".to_string(), focal_length);
+    vec![change]
+}
+
+fn default_focal_length() -> f32 {
+    1.0
+}
+
+/// Fields left at their default are not written, so saving or migrating a
+/// scene file does not fill it with settings it never had.
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
 }
 
 impl Default for CameraData {
@@ -41,8 +210,114 @@ impl Default for CameraData {
             look_at: Point3::new(0.0, 0.0, -1.0),
             up: Vec3::new(0.0, 1.0, 0.0),
             fov: 90.0,
-            aspect_ratio: 1.777,
+            focal_length: 1.0,
+            aspect_ratio: None,
             resolution: (400, 300),
+            projection: ProjectionData::default(),
+            aperture: 0.0,
+            focus_distance: None,
+            blades: 0,
+            near: 0.0,
+            far: None,
+        }
+    }
+}
+
+/// Camera projection; angles are in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ProjectionData {
+    #[default]
+    Perspective,
+    /// View `width` in scene units
+    Orthographic { width: f32 },
+    /// Equidistant fisheye covering `fov` across the image width
+    Fisheye { fov: f32 },
+    Equirectangular,
+}
+
+impl From<ProjectionData> for Projection {
+    fn from(data: ProjectionData) -> Self {
+        match data {
+            ProjectionData::Perspective => Projection::Perspective,
+            ProjectionData::Orthographic { width } => Projection::Orthographic { width },
+            ProjectionData::Fisheye { fov } => Projection::Fisheye { fov },
+            ProjectionData::Equirectangular => Projection::Equirectangular,
+        }
+    }
+}
+
+/// How the scene is rendered and saved. Unset fields are left to the
+/// renderer's flags and defaults, and flags given on the command line win.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RenderData {
+    /// Samples per pixel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samples: Option<u32>,
+    /// Maximum number of bounces per ray
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<u32>,
+    /// Bounces every path takes before Russian roulette may end it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roulette_depth: Option<u32>,
+    /// File to save to; the extension picks the format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub sampler: SamplerData,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub tone_mapping: ToneMappingData,
+    /// Brightness adjustment in stops; each +1 doubles the light
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub exposure: f32,
+}
+
+impl RenderData {
+    /// Samples per pixel when neither the scene file nor the `-q` flag sets them.
+    pub const DEFAULT_SAMPLES: u32 = 32;
+    /// Bounces per ray when neither the scene file nor the `-d` flag sets them.
+    pub const DEFAULT_MAX_DEPTH: u32 = 10;
+
+    pub fn is_empty(&self) -> bool {
+        *self == RenderData::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SamplerData {
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl From<SamplerData> for Sampler {
+    fn from(data: SamplerData) -> Self {
+        match data {
+            SamplerData::Independent => Sampler::Independent,
+            SamplerData::Stratified => Sampler::Stratified,
+            SamplerData::Halton => Sampler::Halton,
+            SamplerData::Sobol => Sampler::Sobol,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ToneMappingData {
+    #[default]
+    None,
+    Reinhard,
+    Aces,
+}
+
+impl From<ToneMappingData> for ToneMapping {
+    fn from(data: ToneMappingData) -> Self {
+        match data {
+            ToneMappingData::None => ToneMapping::None,
+            ToneMappingData::Reinhard => ToneMapping::Reinhard,
+            ToneMappingData::Aces => ToneMapping::Aces,
         }
     }
 }
@@ -53,6 +328,7 @@ pub enum ObjectData {
     Plane(PlaneData),
     Cube(CubeData),
     Cylinder(CylinderData),
+    Mesh(MeshData),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -60,6 +336,8 @@ pub struct SphereData {
     pub center: Point3,
     pub radius: f32,
     pub material: MaterialData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformData>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -67,6 +345,8 @@ pub struct PlaneData {
     pub center: Point3,
     pub size: Vec3,
     pub material: MaterialData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformData>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -74,6 +354,8 @@ pub struct CubeData {
     pub center: Point3,
     pub size: f32,
     pub material: MaterialData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformData>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -82,10 +364,285 @@ pub struct CylinderData {
     pub radius: f32,
     pub height: f32,
     pub material: MaterialData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformData>,
+}
+
+impl ObjectData {
+    /// Reference point of the object, which rotation and scale are applied around.
+    pub fn pivot(&self) -> Point3 {
+        match self {
+            ObjectData::Sphere(s) => s.center,
+            ObjectData::Plane(p) => p.center,
+            ObjectData::Cube(c) => c.center,
+            ObjectData::Cylinder(cy) => cy.center,
+            ObjectData::Mesh(m) => m.position,
+        }
+    }
+
+    pub fn transform(&self) -> Option<&TransformData> {
+        match self {
+            ObjectData::Sphere(s) => s.transform.as_ref(),
+            ObjectData::Plane(p) => p.transform.as_ref(),
+            ObjectData::Cube(c) => c.transform.as_ref(),
+            ObjectData::Cylinder(cy) => cy.transform.as_ref(),
+            ObjectData::Mesh(m) => m.transform.as_ref(),
+        }
+    }
+
+    pub fn transform_mut(&mut self) -> &mut Option<TransformData> {
+        match self {
+            ObjectData::Sphere(s) => &mut s.transform,
+            ObjectData::Plane(p) => &mut p.transform,
+            ObjectData::Cube(c) => &mut c.transform,
+            ObjectData::Cylinder(cy) => &mut cy.transform,
+            ObjectData::Mesh(m) => &mut m.transform,
+        }
+    }
+
+    /// Object-to-world matrix of the object's transform block, if it has one.
+    pub fn transform_matrix(&self) -> Option<Affine3A> {
+        self.transform().map(|t| t.matrix(self.pivot()))
+    }
+}
+
+/// Optional placement applied on top of an object's own parameters.
+/// Scale, then rotation, are applied around the object's pivot before translating.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransformData {
+    pub translation: Vec3,
+    pub rotation: RotationData,
+    pub scale: Vec3,
+}
+
+impl Default for TransformData {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: RotationData::default(),
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl TransformData {
+    pub fn matrix(&self, pivot: Point3) -> Affine3A {
+        let pivot: glam::Vec3 = pivot.into();
+        Affine3A::from_translation(pivot + glam::Vec3::from(self.translation))
+            * Affine3A::from_quat(self.rotation.quat())
+            * Affine3A::from_scale(self.scale.into())
+            * Affine3A::from_translation(-pivot)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RotationData {
+    /// Degrees around the X, then Y, then Z world axes
+    Euler(Vec3),
+    /// Degrees around an arbitrary axis
+    AxisAngle { axis: Vec3, angle: f32 },
+}
+
+impl Default for RotationData {
+    fn default() -> Self {
+        RotationData::Euler(Vec3::ZERO)
+    }
+}
+
+impl RotationData {
+    pub fn quat(&self) -> Quat {
+        match *self {
+            RotationData::Euler(angles) => {
+                Quat::from_rotation_z(angles.z.to_radians())
+                    * Quat::from_rotation_y(angles.y.to_radians())
+                    * Quat::from_rotation_x(angles.x.to_radians())
+            }
+            RotationData::AxisAngle { axis, angle } => {
+                let axis = axis.normalize();
+                if axis.near_zero() {
+                    Quat::IDENTITY
+                } else {
+                    Quat::from_axis_angle(axis.into(), angle.to_radians())
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshData {
+    /// Path to a Wavefront OBJ file
+    pub path: String,
+    pub position: Point3,
+    pub scale: f32,
+    pub material: MaterialData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformData>,
+}
+
+impl Default for MeshData {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            position: Point3::ZERO,
+            scale: 1.0,
+            material: MaterialData::default(),
+            transform: None,
+        }
+    }
+}
+
+/// Analytic light; angles are in degrees and directions need not be normalized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LightData {
+    Point(PointLightData),
+    Spot(SpotLightData),
+    Directional(DirectionalLightData),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointLightData {
+    pub position: Point3,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Default for PointLightData {
+    fn default() -> Self {
+        Self {
+            position: Point3::new(0.0, 5.0, 0.0),
+            color: Color::WHITE,
+            intensity: 200.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpotLightData {
+    pub position: Point3,
+    pub direction: Vec3,
+    /// Half-angle of the cone
+    pub angle: f32,
+    /// Fraction of the cone, from its edge, over which the light fades out (0 = hard edge)
+    #[serde(default)]
+    pub falloff: f32,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Default for SpotLightData {
+    fn default() -> Self {
+        Self {
+            position: Point3::new(0.0, 5.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            angle: 30.0,
+            falloff: 0.2,
+            color: Color::WHITE,
+            intensity: 200.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectionalLightData {
+    /// Direction the light travels in, e.g. straight down for a noon sun
+    pub direction: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Default for DirectionalLightData {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(-1.0, -1.0, -1.0),
+            color: Color::WHITE,
+            intensity: 10.0,
+        }
+    }
+}
+
+/// Material of an object in either model. The classic model is stored
+/// untagged, exactly as before both models existed, so older scene files
+/// still load; PBR materials are tagged as `{ "Pbr": { ... } }`.
+#[derive(Debug, Clone)]
+pub enum MaterialData {
+    Classic(ClassicMaterialData),
+    Pbr(PbrMaterialData),
+}
+
+impl Default for MaterialData {
+    fn default() -> Self {
+        MaterialData::Classic(ClassicMaterialData::default())
+    }
+}
+
+impl MaterialData {
+    /// Base texture of either model.
+    pub fn texture_mut(&mut self) -> &mut TextureData {
+        match self {
+            MaterialData::Classic(m) => &mut m.texture,
+            MaterialData::Pbr(m) => &mut m.base_color,
+        }
+    }
+
+    pub fn emission_mut(&mut self) -> &mut Option<Color> {
+        match self {
+            MaterialData::Classic(m) => &mut m.emission,
+            MaterialData::Pbr(m) => &mut m.emission,
+        }
+    }
+}
+
+impl Serialize for MaterialData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MaterialData::Classic(m) => m.serialize(serializer),
+            MaterialData::Pbr(m) => serializer.serialize_newtype_variant("MaterialData", 1, "Pbr", m),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MaterialData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let material = match value.get("Pbr") {
+            Some(pbr) => PbrMaterialData::deserialize(pbr).map(MaterialData::Pbr),
+            None => ClassicMaterialData::deserialize(value).map(MaterialData::Classic),
+        };
+        material.map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PbrMaterialData {
+    pub base_color: TextureData,
+    pub metallic: f32,
+    pub roughness: f32,
+    #[serde(default = "default_specular")]
+    pub specular: f32,
+    #[serde(default)]
+    pub emission: Option<Color>,
+}
+
+fn default_specular() -> f32 {
+    0.5
+}
+
+impl Default for PbrMaterialData {
+    fn default() -> Self {
+        Self {
+            base_color: TextureData::default(),
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: default_specular(),
+            emission: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct MaterialData {
+pub struct ClassicMaterialData {
     pub texture: TextureData,
     pub diffuse: f32,
     pub reflectivity: f32,
@@ -94,7 +651,7 @@ pub struct MaterialData {
     pub emission: Option<Color>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TextureData {
     SolidColor(Color),
     Gradient(Color, Color, f32),
@@ -108,52 +665,303 @@ impl Default for TextureData {
     }
 }
 
-impl From<TextureData> for Texture {
-    fn from(data: TextureData) -> Self {
+impl From<CameraData> for Camera {
+    fn from(data: CameraData) -> Self {
+        let mut camera = Camera::new();
+        camera.set(
+            data.position,
+            data.look_at,
+            data.up,
+            data.fov,
+            data.focal_length,
+            data.resolution,
+        );
+        camera.set_aspect_ratio(data.aspect_ratio);
+        camera.set_projection(data.projection.into());
+        camera.set_lens(data.aperture, data.focus_distance, data.blades);
+        camera.set_clip(data.near, data.far);
+        camera
+    }
+}
+
+impl From<LightData> for Light {
+    fn from(data: LightData) -> Self {
         match data {
-            TextureData::SolidColor(c) => Texture::SolidColor(c),
-            TextureData::Gradient(c1, c2, angle) => Texture::Gradient(c1, c2, angle),
-            TextureData::Checkerboard(c1, c2, freq) => Texture::Checkerboard(c1, c2, freq),
-            TextureData::Image(path) => Texture::Image(Arc::new(Image::load(&path).unwrap())),
+            LightData::Point(p) => Light::Point {
+                position: p.position,
+                color: p.color,
+                intensity: p.intensity,
+            },
+            LightData::Spot(s) => Light::Spot {
+                position: s.position,
+                direction: s.direction,
+                angle: s.angle,
+                falloff: s.falloff,
+                color: s.color,
+                intensity: s.intensity,
+            },
+            LightData::Directional(d) => Light::Directional {
+                direction: d.direction,
+                color: d.color,
+                intensity: d.intensity,
+            },
         }
     }
 }
 
-use crate::material::Material;
+/// Builds the renderer's objects from a scene document, loading the files it
+/// refers to. Relative paths are taken from the directory of the scene file,
+/// and an image used by several textures is decoded only once.
+pub struct SceneLoader {
+    base_dir: PathBuf,
+    images: HashMap<PathBuf, Arc<Image>>,
+}
 
-impl From<MaterialData> for Material {
-    fn from(data: MaterialData) -> Self {
-        Material {
-            texture: data.texture.into(),
-            diffuse: data.diffuse,
-            reflectivity: data.reflectivity,
-            transparency: data.transparency,
-            index_of_refraction: data.index_of_refraction,
-            emission: data.emission,
+impl SceneLoader {
+    /// Loader resolving relative paths from `base_dir`.
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            images: HashMap::new(),
         }
     }
-}
 
-impl From<SphereData> for Sphere {
-    fn from(data: SphereData) -> Self {
-        Sphere::new(data.center, data.radius, data.material.into())
+    /// Loader for the scene file at `path`.
+    pub fn for_scene_file(path: impl AsRef<Path>) -> Self {
+        Self::new(path.as_ref().parent().unwrap_or(Path::new("")))
     }
-}
 
-impl From<PlaneData> for Plane {
-    fn from(data: PlaneData) -> Self {
-        Plane::new(data.center, data.size, data.material.into())
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
     }
-}
 
-impl From<CubeData> for Cube {
-    fn from(data: CubeData) -> Self {
-        Cube::new(data.center, data.size, data.material.into())
+    /// File a path in the scene document refers to. Absolute paths are kept as they are.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        self.base_dir.join(path)
+    }
+
+    pub fn image(&mut self, path: &str) -> io::Result<Arc<Image>> {
+        let file = self.resolve(path);
+        // Different spellings of the same file share one image
+        let key = fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+        if let Some(image) = self.images.get(&key) {
+            return Ok(image.clone());
+        }
+
+        let image = Image::load(&file)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", file.display())))?;
+        let image = Arc::new(image);
+        self.images.insert(key, image.clone());
+        Ok(image)
+    }
+
+    pub fn texture(&mut self, data: TextureData) -> io::Result<Texture> {
+        Ok(match data {
+            TextureData::SolidColor(c) => Texture::SolidColor(c),
+            TextureData::Gradient(c1, c2, angle) => Texture::Gradient(c1, c2, angle),
+            TextureData::Checkerboard(c1, c2, freq) => Texture::Checkerboard(c1, c2, freq),
+            TextureData::Image(path) => Texture::Image(self.image(&path)?),
+        })
+    }
+
+    pub fn material(&mut self, data: MaterialData) -> io::Result<Arc<dyn Scatter>> {
+        Ok(match data {
+            MaterialData::Classic(m) => Material {
+                texture: self.texture(m.texture)?,
+                diffuse: m.diffuse,
+                reflectivity: m.reflectivity,
+                transparency: m.transparency,
+                index_of_refraction: m.index_of_refraction,
+                emission: m.emission,
+            }
+            .into(),
+            MaterialData::Pbr(m) => PbrMaterial {
+                base_color: self.texture(m.base_color)?,
+                metallic: m.metallic,
+                roughness: m.roughness,
+                specular: m.specular,
+                emission: m.emission,
+            }
+            .into(),
+        })
+    }
+
+    /// Build an object, leaving out its transform block.
+    pub fn object(&mut self, data: ObjectData) -> io::Result<Box<dyn Hittable>> {
+        Ok(match data {
+            ObjectData::Sphere(s) => {
+                let material = self.material(s.material)?;
+                Box::new(Sphere::new(s.center, s.radius, material))
+            }
+            ObjectData::Plane(p) => {
+                let material = self.material(p.material)?;
+                Box::new(Plane::new(p.center, p.size, material))
+            }
+            ObjectData::Cube(c) => {
+                let material = self.material(c.material)?;
+                Box::new(Cube::new(c.center, c.size, material))
+            }
+            ObjectData::Cylinder(cy) => {
+                let material = self.material(cy.material)?;
+                Box::new(Cylinder::new(cy.center, cy.radius, cy.height, material))
+            }
+            ObjectData::Mesh(m) => {
+                let material = self.material(m.material)?;
+                let file = self.resolve(&m.path);
+                let mesh = Mesh::load_obj(&file, m.position, m.scale, material)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", file.display())))?;
+                Box::new(mesh)
+            }
+        })
     }
 }
 
-impl From<CylinderData> for Cylinder {
-    fn from(data: CylinderData) -> Self {
-        Cylinder::new(data.center, data.radius, data.height, data.material.into())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A scene as written before the format had a version.
+    fn version_zero() -> Value {
+        json!({
+            "objects": [{
+                "Sphere": {
+                    "center": { "x": 0.0, "y": 1.0, "z": 0.0 },
+                    "radius": 1.0,
+                    "material": {
+                        "texture": { "SolidColor": { "r": 1.0, "g": 0.5, "b": 0.0 } },
+                        "diffuse": 1.0,
+                        "reflectivity": 0.0,
+                        "transparency": 0.0,
+                        "index_of_refraction": 0.0,
+                        "emission": null
+                    }
+                }
+            }],
+            "camera": {
+                "position": { "x": 4.0, "y": 2.0, "z": 4.0 },
+                "look_at": { "x": 0.0, "y": 0.0, "z": 0.0 },
+                "up": { "x": 0.0, "y": 1.0, "z": 0.0 },
+                "fov": 60.0,
+                "aspect_ratio": 1.5,
+                "resolution": [800, 600]
+            },
+            "background": { "SolidColor": { "r": 0.1, "g": 0.1, "b": 0.1 } }
+        })
+    }
+
+    /// The document `data` is saved as. Going through text keeps numbers as
+    /// the `f32` they are stored as, like in a saved file.
+    fn written(data: &SceneData) -> Value {
+        serde_json::from_str(&serde_json::to_string(data).unwrap()).unwrap()
     }
-}
\ No newline at end of file
+
+    fn error_message(mut value: Value) -> String {
+        migrate(&mut value).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn upgrades_version_zero_and_reports_the_changes() {
+        let mut value = version_zero();
+        let report = migrate(&mut value).unwrap();
+
+        assert_eq!(report.from_version, 0);
+        assert!(report.upgraded());
+        assert_eq!(
+            report.changes,
+            vec!["0 -> 1: camera `aspect_ratio` 1.5 is now `focal_length`"]
+        );
+
+        let mut expected = version_zero();
+        let camera = expected["camera"].as_object_mut().unwrap();
+        camera.remove("aspect_ratio");
+        camera.insert("focal_length".to_string(), json!(1.5));
+        expected["version"] = json!(SCENE_VERSION);
+        assert_eq!(value, expected);
+
+        let data: SceneData = serde_json::from_value(value).unwrap();
+        assert_eq!(data.camera.focal_length, 1.5);
+        assert_eq!(data.camera.aspect_ratio, None);
+    }
+
+    #[test]
+    fn version_zero_without_the_old_field_is_only_stamped() {
+        let mut value = version_zero();
+        value["camera"]
+            .as_object_mut()
+            .unwrap()
+            .remove("aspect_ratio");
+        let report = migrate(&mut value).unwrap();
+
+        assert!(report.upgraded());
+        assert!(report.changes.is_empty());
+        assert_eq!(value["version"], json!(SCENE_VERSION));
+        assert!(value["camera"].get("focal_length").is_none());
+    }
+
+    #[test]
+    fn leaves_current_documents_untouched() {
+        let mut value = version_zero();
+        migrate(&mut value).unwrap();
+        // In the current version `aspect_ratio` is the real aspect ratio
+        value["camera"]["aspect_ratio"] = json!(2.0);
+        let current = value.clone();
+
+        let report = migrate(&mut value).unwrap();
+        assert_eq!(report.from_version, SCENE_VERSION);
+        assert!(!report.upgraded());
+        assert!(report.changes.is_empty());
+        assert_eq!(value, current);
+    }
+
+    #[test]
+    fn rejects_newer_and_invalid_versions() {
+        let mut newer = version_zero();
+        newer["version"] = json!(SCENE_VERSION + 1);
+        assert_eq!(
+            error_message(newer),
+            format!(
+                "scene version {} is newer than the latest supported version {SCENE_VERSION}",
+                SCENE_VERSION + 1
+            )
+        );
+
+        for version in [json!(-1), json!(1.5), json!("1"), json!(u64::MAX)] {
+            let mut value = version_zero();
+            value["version"] = version.clone();
+            assert_eq!(
+                error_message(value),
+                format!("invalid scene version {version}")
+            );
+        }
+
+        assert_eq!(error_message(json!([])), "a scene must be a JSON object");
+    }
+
+    /// Writing a migrated scene back out only adds what the migration changed.
+    #[test]
+    fn migrated_scenes_are_written_without_defaults() {
+        let json = version_zero().to_string();
+        let (data, _) = SceneData::from_json_migrated(&json).unwrap();
+
+        let mut expected = version_zero();
+        migrate(&mut expected).unwrap();
+        assert_eq!(written(&data), expected);
+    }
+
+    #[test]
+    fn bundled_scenes_are_current_and_round_trip() {
+        let scenes = [
+            include_str!("../../scene1.json"),
+            include_str!("../../scene3.json"),
+            include_str!("../../scene5.json"),
+            include_str!("../../big_lights.json"),
+        ];
+        for json in scenes {
+            let (data, report) = SceneData::from_json_migrated(json).unwrap();
+            assert!(!report.upgraded());
+            let original: Value = serde_json::from_str(json).unwrap();
+            assert_eq!(written(&data), original);
+        }
+    }
+}
@@ -0,0 +1,625 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::core::color::Color;
+use crate::core::vec3::Vec3;
+use crate::scene::storage::*;
+
+/// Something wrong with one value of a scene document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneProblem {
+    /// JSON path of the offending value, such as `objects[2].Sphere.radius`
+    pub path: String,
+    pub message: String,
+}
+
+impl SceneProblem {
+    pub fn new(path: impl Into<String>, message: impl fmt::Display) -> Self {
+        Self {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SceneProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Why a scene file could not be loaded.
+#[derive(Debug)]
+pub enum SceneError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not a scene document
+    Parse(serde_json::Error),
+    /// The document was read, but describes a scene that cannot be built.
+    /// Every problem found is listed, not just the first.
+    Invalid(Vec<SceneProblem>),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "could not read the file: {e}"),
+            SceneError::Parse(e) => write!(f, "not a valid scene document: {e}"),
+            SceneError::Invalid(problems) => {
+                let plural = if problems.len() == 1 { "" } else { "s" };
+                write!(f, "{} problem{plural} found", problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(e) => Some(e),
+            SceneError::Parse(e) => Some(e),
+            SceneError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(e: io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(e: serde_json::Error) -> Self {
+        SceneError::Parse(e)
+    }
+}
+
+impl SceneData {
+    /// Look for values the renderer cannot work with, such as negative sizes,
+    /// infinite coordinates or files that do not exist, without loading anything.
+    /// Relative file paths are looked up from `base_dir`, the scene file's directory.
+    pub fn validate(&self, base_dir: &Path) -> Vec<SceneProblem> {
+        let mut check = Checker {
+            base_dir,
+            problems: Vec::new(),
+        };
+
+        check.camera("camera", &self.camera);
+        check.texture("background", &self.background);
+        for (i, object) in self.objects.iter().enumerate() {
+            check.object(&format!("objects[{i}]"), object);
+        }
+        for (i, light) in self.lights.iter().enumerate() {
+            check.light(&format!("lights[{i}]"), light);
+        }
+        check.finite("render.exposure", self.render.exposure);
+
+        check.problems
+    }
+}
+
+struct Checker<'a> {
+    base_dir: &'a Path,
+    problems: Vec<SceneProblem>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, path: &str, message: impl fmt::Display) {
+        self.problems.push(SceneProblem::new(path, message));
+    }
+
+    fn finite(&mut self, path: &str, value: f32) -> bool {
+        if !value.is_finite() {
+            self.report(path, format!("must be a finite number, got {value}"));
+        }
+        value.is_finite()
+    }
+
+    fn positive(&mut self, path: &str, value: f32) {
+        if self.finite(path, value) && value <= 0.0 {
+            self.report(path, format!("must be greater than 0, got {value}"));
+        }
+    }
+
+    fn vector(&mut self, path: &str, v: Vec3) -> bool {
+        let finite = v.x.is_finite() && v.y.is_finite() && v.z.is_finite();
+        if !finite {
+            self.report(path, format!("must have finite components, got {v}"));
+        }
+        finite
+    }
+
+    /// A direction must also have a length to take the direction of.
+    fn direction(&mut self, path: &str, v: Vec3) {
+        if self.vector(path, v) && v.length_squared() == 0.0 {
+            self.report(path, "must not be the zero vector");
+        }
+    }
+
+    fn color(&mut self, path: &str, c: Color) {
+        if !(c.r.is_finite() && c.g.is_finite() && c.b.is_finite()) {
+            self.report(
+                path,
+                format!(
+                    "must have finite components, got ({}, {}, {})",
+                    c.r, c.g, c.b
+                ),
+            );
+        }
+    }
+
+    fn file(&mut self, path: &str, file: &str) {
+        if file.is_empty() {
+            self.report(path, "no file given");
+        } else if !self.base_dir.join(file).is_file() {
+            self.report(path, format!("file not found: {file}"));
+        }
+    }
+
+    fn camera(&mut self, path: &str, camera: &CameraData) {
+        let position = self.vector(&format!("{path}.position"), camera.position);
+        let look_at = self.vector(&format!("{path}.look_at"), camera.look_at);
+        let up = self.vector(&format!("{path}.up"), camera.up);
+        if position && look_at && up {
+            let forward = camera.look_at - camera.position;
+            if forward.length_squared() == 0.0 {
+                self.report(
+                    &format!("{path}.look_at"),
+                    "must differ from the camera position",
+                );
+            } else if forward.cross(camera.up).length_squared() == 0.0 {
+                self.report(
+                    &format!("{path}.up"),
+                    "must not point along the view direction",
+                );
+            }
+        }
+
+        if camera.projection == ProjectionData::Perspective
+            && self.finite(&format!("{path}.fov"), camera.fov)
+            && !(camera.fov > 0.0 && camera.fov < 180.0)
+        {
+            self.report(
+                &format!("{path}.fov"),
+                format!("must be between 0 and 180 degrees, got {}", camera.fov),
+            );
+        }
+        self.positive(&format!("{path}.focal_length"), camera.focal_length);
+        if let Some(aspect_ratio) = camera.aspect_ratio {
+            self.positive(&format!("{path}.aspect_ratio"), aspect_ratio);
+        }
+        let (width, height) = camera.resolution;
+        if width == 0 || height == 0 {
+            self.report(
+                &format!("{path}.resolution"),
+                format!("must be at least 1x1, got {width}x{height}"),
+            );
+        }
+        match camera.projection {
+            ProjectionData::Orthographic { width } => {
+                self.positive(&format!("{path}.projection.Orthographic.width"), width)
+            }
+            ProjectionData::Fisheye { fov } => {
+                self.positive(&format!("{path}.projection.Fisheye.fov"), fov)
+            }
+            ProjectionData::Perspective | ProjectionData::Equirectangular => {}
+        }
+        if self.finite(&format!("{path}.aperture"), camera.aperture) && camera.aperture < 0.0 {
+            self.report(
+                &format!("{path}.aperture"),
+                format!("must not be negative, got {}", camera.aperture),
+            );
+        }
+        if let Some(distance) = camera.focus_distance {
+            self.positive(&format!("{path}.focus_distance"), distance);
+        }
+        let near = self.finite(&format!("{path}.near"), camera.near);
+        if near && camera.near < 0.0 {
+            self.report(
+                &format!("{path}.near"),
+                format!("must not be negative, got {}", camera.near),
+            );
+        }
+        if let Some(far) = camera.far {
+            self.positive(&format!("{path}.far"), far);
+            if near && far > 0.0 && far <= camera.near {
+                self.report(
+                    &format!("{path}.far"),
+                    format!("must be beyond near ({}), got {far}", camera.near),
+                );
+            }
+        }
+    }
+
+    fn object(&mut self, path: &str, object: &ObjectData) {
+        let (path, material, transform) = match object {
+            ObjectData::Sphere(s) => {
+                let path = format!("{path}.Sphere");
+                self.vector(&format!("{path}.center"), s.center);
+                // A negative radius turns the sphere inside out, for hollow glass
+                if self.finite(&format!("{path}.radius"), s.radius) && s.radius == 0.0 {
+                    self.report(&format!("{path}.radius"), "must not be 0");
+                }
+                (path, &s.material, &s.transform)
+            }
+            ObjectData::Plane(p) => {
+                let path = format!("{path}.Plane");
+                self.vector(&format!("{path}.center"), p.center);
+                // Planes lie flat, so only the x and z extents matter
+                if self.vector(&format!("{path}.size"), p.size)
+                    && (p.size.x <= 0.0 || p.size.z <= 0.0)
+                {
+                    self.report(
+                        &format!("{path}.size"),
+                        format!("x and z must be greater than 0, got {}", p.size),
+                    );
+                }
+                (path, &p.material, &p.transform)
+            }
+            ObjectData::Cube(c) => {
+                let path = format!("{path}.Cube");
+                self.vector(&format!("{path}.center"), c.center);
+                self.positive(&format!("{path}.size"), c.size);
+                (path, &c.material, &c.transform)
+            }
+            ObjectData::Cylinder(cy) => {
+                let path = format!("{path}.Cylinder");
+                self.vector(&format!("{path}.center"), cy.center);
+                self.positive(&format!("{path}.radius"), cy.radius);
+                self.positive(&format!("{path}.height"), cy.height);
+                (path, &cy.material, &cy.transform)
+            }
+            ObjectData::Mesh(m) => {
+                let path = format!("{path}.Mesh");
+                self.file(&format!("{path}.path"), &m.path);
+                self.vector(&format!("{path}.position"), m.position);
+                self.positive(&format!("{path}.scale"), m.scale);
+                (path, &m.material, &m.transform)
+            }
+        };
+
+        self.material(&format!("{path}.material"), material);
+        if let Some(transform) = transform {
+            self.transform(&format!("{path}.transform"), transform);
+        }
+    }
+
+    fn transform(&mut self, path: &str, transform: &TransformData) {
+        self.vector(&format!("{path}.translation"), transform.translation);
+        let scale = transform.scale;
+        // A zero scale flattens the object and cannot be inverted to trace rays
+        if self.vector(&format!("{path}.scale"), scale)
+            && (scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0)
+        {
+            self.report(
+                &format!("{path}.scale"),
+                format!("must not have a zero component, got {scale}"),
+            );
+        }
+        match transform.rotation {
+            RotationData::Euler(angles) => {
+                self.vector(&format!("{path}.rotation.Euler"), angles);
+            }
+            RotationData::AxisAngle { axis, angle } => {
+                self.vector(&format!("{path}.rotation.AxisAngle.axis"), axis);
+                self.finite(&format!("{path}.rotation.AxisAngle.angle"), angle);
+            }
+        }
+    }
+
+    fn material(&mut self, path: &str, material: &MaterialData) {
+        match material {
+            MaterialData::Classic(m) => {
+                self.texture(&format!("{path}.texture"), &m.texture);
+                self.finite(&format!("{path}.diffuse"), m.diffuse);
+                self.finite(&format!("{path}.reflectivity"), m.reflectivity);
+                self.finite(&format!("{path}.transparency"), m.transparency);
+                self.finite(
+                    &format!("{path}.index_of_refraction"),
+                    m.index_of_refraction,
+                );
+                if let Some(emission) = m.emission {
+                    self.color(&format!("{path}.emission"), emission);
+                }
+            }
+            MaterialData::Pbr(m) => {
+                let path = format!("{path}.Pbr");
+                self.texture(&format!("{path}.base_color"), &m.base_color);
+                self.finite(&format!("{path}.metallic"), m.metallic);
+                self.finite(&format!("{path}.roughness"), m.roughness);
+                self.finite(&format!("{path}.specular"), m.specular);
+                if let Some(emission) = m.emission {
+                    self.color(&format!("{path}.emission"), emission);
+                }
+            }
+        }
+    }
+
+    fn texture(&mut self, path: &str, texture: &TextureData) {
+        match texture {
+            TextureData::SolidColor(c) => self.color(&format!("{path}.SolidColor"), *c),
+            TextureData::Gradient(c1, c2, angle) => {
+                self.color(&format!("{path}.Gradient[0]"), *c1);
+                self.color(&format!("{path}.Gradient[1]"), *c2);
+                self.finite(&format!("{path}.Gradient[2]"), *angle);
+            }
+            TextureData::Checkerboard(c1, c2, frequency) => {
+                self.color(&format!("{path}.Checkerboard[0]"), *c1);
+                self.color(&format!("{path}.Checkerboard[1]"), *c2);
+                self.finite(&format!("{path}.Checkerboard[2]"), *frequency);
+            }
+            TextureData::Image(file) => self.file(&format!("{path}.Image"), file),
+        }
+    }
+
+    fn light(&mut self, path: &str, light: &LightData) {
+        match light {
+            LightData::Point(p) => {
+                let path = format!("{path}.Point");
+                self.vector(&format!("{path}.position"), p.position);
+                self.color(&format!("{path}.color"), p.color);
+                self.finite(&format!("{path}.intensity"), p.intensity);
+            }
+            LightData::Spot(s) => {
+                let path = format!("{path}.Spot");
+                self.vector(&format!("{path}.position"), s.position);
+                self.direction(&format!("{path}.direction"), s.direction);
+                self.finite(&format!("{path}.angle"), s.angle);
+                self.finite(&format!("{path}.falloff"), s.falloff);
+                self.color(&format!("{path}.color"), s.color);
+                self.finite(&format!("{path}.intensity"), s.intensity);
+            }
+            LightData::Directional(d) => {
+                let path = format!("{path}.Directional");
+                self.direction(&format!("{path}.direction"), d.direction);
+                self.color(&format!("{path}.color"), d.color);
+                self.finite(&format!("{path}.intensity"), d.intensity);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Scene;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn sphere(radius: f32) -> ObjectData {
+        ObjectData::Sphere(SphereData {
+            radius,
+            ..SphereData::default()
+        })
+    }
+
+    fn problems(data: &SceneData) -> Vec<(String, String)> {
+        data.validate(Path::new("."))
+            .into_iter()
+            .map(|problem| (problem.path, problem.message))
+            .collect()
+    }
+
+    fn problem(path: &str, message: &str) -> (String, String) {
+        (path.to_string(), message.to_string())
+    }
+
+    /// An empty directory for files a test writes, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rt_2-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn accepts_a_valid_scene() {
+        let data = SceneData {
+            objects: vec![sphere(1.0), sphere(-0.9)],
+            lights: vec![LightData::Spot(SpotLightData::default())],
+            ..SceneData::default()
+        };
+        assert_eq!(problems(&data), vec![]);
+    }
+
+    #[test]
+    fn reports_every_problem_with_its_path() {
+        let mut data = SceneData {
+            objects: vec![
+                sphere(1.0),
+                ObjectData::Cube(CubeData {
+                    size: -1.0,
+                    ..CubeData::default()
+                }),
+                sphere(0.0),
+                ObjectData::Cylinder(CylinderData {
+                    radius: f32::NAN,
+                    height: 2.0,
+                    material: MaterialData::Pbr(PbrMaterialData {
+                        metallic: f32::INFINITY,
+                        ..PbrMaterialData::default()
+                    }),
+                    transform: Some(TransformData {
+                        scale: Vec3::new(1.0, 0.0, 1.0),
+                        ..TransformData::default()
+                    }),
+                    ..CylinderData::default()
+                }),
+            ],
+            lights: vec![LightData::Spot(SpotLightData {
+                direction: Vec3::ZERO,
+                ..SpotLightData::default()
+            })],
+            ..SceneData::default()
+        };
+        data.camera.fov = 200.0;
+        data.camera.resolution = (0, 600);
+        data.camera.near = 5.0;
+        data.camera.far = Some(2.0);
+        data.render.exposure = f32::INFINITY;
+
+        assert_eq!(
+            problems(&data),
+            vec![
+                problem("camera.fov", "must be between 0 and 180 degrees, got 200"),
+                problem("camera.resolution", "must be at least 1x1, got 0x600"),
+                problem("camera.far", "must be beyond near (5), got 2"),
+                problem("objects[1].Cube.size", "must be greater than 0, got -1"),
+                problem("objects[2].Sphere.radius", "must not be 0"),
+                problem(
+                    "objects[3].Cylinder.radius",
+                    "must be a finite number, got NaN"
+                ),
+                problem(
+                    "objects[3].Cylinder.material.Pbr.metallic",
+                    "must be a finite number, got inf"
+                ),
+                problem(
+                    "objects[3].Cylinder.transform.scale",
+                    "must not have a zero component, got 1 0 1"
+                ),
+                problem("lights[0].Spot.direction", "must not be the zero vector"),
+                problem("render.exposure", "must be a finite number, got inf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_a_camera_that_cannot_look_anywhere() {
+        let mut data = SceneData::default();
+        data.camera.look_at = data.camera.position;
+        assert_eq!(
+            problems(&data),
+            vec![problem(
+                "camera.look_at",
+                "must differ from the camera position"
+            )]
+        );
+
+        data.camera.look_at = data.camera.position + data.camera.up;
+        assert_eq!(
+            problems(&data),
+            vec![problem(
+                "camera.up",
+                "must not point along the view direction"
+            )]
+        );
+    }
+
+    #[test]
+    fn looks_files_up_from_the_scene_directory() {
+        let dir = TempDir::new("validation-files");
+        fs::write(
+            dir.0.join("cube.obj"),
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+        )
+        .unwrap();
+
+        let material = ClassicMaterialData {
+            texture: TextureData::Image("textures/missing.png".to_string()),
+            ..ClassicMaterialData::default()
+        };
+        let data = SceneData {
+            objects: vec![
+                ObjectData::Mesh(MeshData {
+                    path: "cube.obj".to_string(),
+                    ..MeshData::default()
+                }),
+                ObjectData::Mesh(MeshData {
+                    path: "other.obj".to_string(),
+                    material: MaterialData::Classic(material),
+                    ..MeshData::default()
+                }),
+                ObjectData::Mesh(MeshData::default()),
+            ],
+            ..SceneData::default()
+        };
+
+        let found: Vec<_> = data
+            .validate(&dir.0)
+            .into_iter()
+            .map(|problem| problem.to_string())
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                "objects[1].Mesh.path: file not found: other.obj",
+                "objects[1].Mesh.material.texture.Image: file not found: textures/missing.png",
+                "objects[2].Mesh.path: no file given",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_files_that_fail_to_load() {
+        let dir = TempDir::new("validation-load");
+        fs::write(
+            dir.0.join("good.obj"),
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.0.join("bad.obj"),
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n",
+        )
+        .unwrap();
+
+        let data = SceneData {
+            objects: vec![
+                ObjectData::Mesh(MeshData {
+                    path: "good.obj".to_string(),
+                    ..MeshData::default()
+                }),
+                ObjectData::Mesh(MeshData {
+                    path: "bad.obj".to_string(),
+                    ..MeshData::default()
+                }),
+            ],
+            ..SceneData::default()
+        };
+        let file = dir.0.join("scene.json");
+        fs::write(&file, serde_json::to_string(&data).unwrap()).unwrap();
+
+        let Err(SceneError::Invalid(problems)) = Scene::load_from_file(file.to_str().unwrap())
+        else {
+            panic!("a face index out of range must fail the load");
+        };
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, "objects[1]");
+        assert!(
+            problems[0]
+                .message
+                .ends_with("bad.obj: line 4: bad face index"),
+            "{}",
+            problems[0].message
+        );
+    }
+
+    #[test]
+    fn lists_the_problems_in_the_error_message() {
+        let error = SceneError::Invalid(vec![
+            SceneProblem::new("objects[2].Sphere.radius", "must not be 0"),
+            SceneProblem::new("render.exposure", "must be a finite number, got inf"),
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "2 problems found\n  objects[2].Sphere.radius: must not be 0\n  render.exposure: must be a finite number, got inf"
+        );
+    }
+}
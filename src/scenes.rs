@@ -1,13 +1,15 @@
+//! The built-in example scenes, rendered with `-s 1` to `-s 8`.
+
 use std::f32::consts::PI;
 use std::sync::Arc;
 
-use rt_2::core::*;
-use rt_2::material::*;
-use rt_2::objects::*;
-use rt_2::particle_system::*;
-use rt_2::pixels::*;
-use rt_2::random_float;
-use rt_2::scene::*;
+use crate::core::*;
+use crate::material::*;
+use crate::objects::*;
+use crate::particle_system::*;
+use crate::pixels::*;
+use crate::random_float;
+use crate::scene::*;
 
 pub fn scene_one(scene: &mut Scene) {
     scene.set_background(Texture::Gradient(Color::LIGHT_BLUE, Color::NEON_BLUE, PI));